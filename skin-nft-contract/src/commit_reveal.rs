@@ -0,0 +1,13 @@
+use crate::*;
+
+/// blocks after the commit during which a loot or fuse request can be revealed
+pub const REQUEST_EXPIRY_BLOCKS: u64 = 1_000;
+
+/// seed of a reveal, the salt keeps the block producer from picking the outcome alone.
+/// nonce tells apart requests revealed in the same block
+pub fn reveal_seed(random_seed: &[u8], salt: &str, nonce: u64) -> Vec<u8> {
+    let mut seed = random_seed.to_vec();
+    seed.extend_from_slice(salt.as_bytes());
+    seed.extend_from_slice(&nonce.to_le_bytes());
+    env::sha256(&seed)
+}
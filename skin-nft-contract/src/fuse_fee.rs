@@ -0,0 +1,180 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
+const GAS_FOR_RESOLVE_WITHDRAW_FUSE_FEES: Gas = 10_000_000_000_000;
+
+#[ext_contract(ext_fungible_token)]
+trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[ext_contract(ext_fuse_fee_resolver)]
+trait FuseFeeResolver {
+    fn resolve_withdraw_fuse_fees(
+        &mut self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    ) -> bool;
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sends the collected fuse fees of account_id (treasury by default) in ft_contract_id
+    #[payable]
+    pub fn withdraw_fuse_fees(
+        &mut self,
+        ft_contract_id: ValidAccountId,
+        account_id: Option<ValidAccountId>,
+    ) -> Promise {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+
+        let ft_contract_id: AccountId = ft_contract_id.into();
+        let account_id: AccountId = account_id
+            .map(|account_id| account_id.into())
+            .unwrap_or_else(|| self.treasury_id.clone());
+
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let amount = self.fuse_fees.remove(&key).unwrap_or(0);
+        assert!(amount > 0, "Skins: no fuse fees to withdraw");
+
+        self.internal_send_fuse_fees(ft_contract_id, account_id, amount, "Skins: fuse fees")
+    }
+
+    /// Puts the amount back to the ledger when ft_transfer failed
+    #[private]
+    pub fn resolve_withdraw_fuse_fees(
+        &mut self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        let is_success = matches!(env::promise_result(0), PromiseResult::Successful(_));
+
+        if !is_success {
+            self.internal_add_fuse_fee(&ft_contract_id, &account_id, amount.0);
+        }
+
+        env::log(
+            json!({
+                "type": "withdraw_fuse_fees",
+                "params": {
+                    "ft_contract_id": ft_contract_id,
+                    "account_id": account_id,
+                    "amount": amount,
+                    "is_success": is_success,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        is_success
+    }
+
+    /// share of each fuse fee for the treasury, the rest goes to the series creator
+    pub fn set_fuse_treasury_fee(&mut self, fuse_treasury_fee: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        assert!(
+            fuse_treasury_fee <= 10_000,
+            "Skins: fuse_treasury_fee must be at most 10000"
+        );
+        self.fuse_treasury_fee = fuse_treasury_fee;
+    }
+
+    pub fn get_fuse_treasury_fee(&self) -> u32 {
+        self.fuse_treasury_fee
+    }
+
+    pub fn get_fuse_fees(&self, ft_contract_id: ValidAccountId, account_id: ValidAccountId) -> U128 {
+        U128(
+            self.fuse_fees
+                .get(&(ft_contract_id.into(), account_id.into()))
+                .unwrap_or(0),
+        )
+    }
+}
+
+impl Contract {
+    /// Splits a collected fuse fee between the treasury and the series creator
+    pub(crate) fn internal_collect_fuse_fee(
+        &mut self,
+        ft_contract_id: &AccountId,
+        creator_id: &AccountId,
+        amount: Balance,
+    ) {
+        let treasury_amount = amount * self.fuse_treasury_fee as u128 / 10_000;
+        let treasury_id = self.treasury_id.clone();
+
+        self.internal_add_fuse_fee(ft_contract_id, &treasury_id, treasury_amount);
+        self.internal_add_fuse_fee(ft_contract_id, creator_id, amount - treasury_amount);
+    }
+
+    /// Splits a fuse fee paid in NEAR like internal_collect_fuse_fee and transfers both shares
+    pub(crate) fn internal_pay_near_fuse_fee(&self, creator_id: &AccountId, amount: Balance) {
+        let treasury_amount = amount * self.fuse_treasury_fee as u128 / 10_000;
+
+        if treasury_amount > 0 {
+            Promise::new(self.treasury_id.clone()).transfer(treasury_amount);
+        }
+        if amount > treasury_amount {
+            Promise::new(creator_id.clone()).transfer(amount - treasury_amount);
+        }
+    }
+
+    /// ft_transfer from the contract, the amount goes to the fuse fees of account_id when it fails
+    pub(crate) fn internal_send_fuse_fees(
+        &self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: Balance,
+        memo: &str,
+    ) -> Promise {
+        ft_transfer(&ft_contract_id, &account_id, amount, memo).then(
+            ext_fuse_fee_resolver::resolve_withdraw_fuse_fees(
+                ft_contract_id,
+                account_id,
+                U128(amount),
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_WITHDRAW_FUSE_FEES,
+            ),
+        )
+    }
+
+    pub(crate) fn internal_add_fuse_fee(&mut self, ft_contract_id: &AccountId, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return;
+        }
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let balance = self.fuse_fees.get(&key).unwrap_or(0);
+        self.fuse_fees.insert(&key, &(balance + amount));
+    }
+}
+
+/// ft_transfer of amount from the contract to account_id
+pub(crate) fn ft_transfer(
+    ft_contract_id: &AccountId,
+    account_id: &AccountId,
+    amount: Balance,
+    memo: &str,
+) -> Promise {
+    ext_fungible_token::ft_transfer(
+        account_id.clone(),
+        U128(amount),
+        Some(memo.to_string()),
+        ft_contract_id,
+        1,
+        GAS_FOR_FT_TRANSFER,
+    )
+}
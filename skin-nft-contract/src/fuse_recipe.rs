@@ -0,0 +1,470 @@
+use std::fmt::Display;
+
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::Value;
+
+use crate::TokenSeriesId;
+
+/// fuse_targets_by_ingredient key for ingredients that accept any series
+pub const FUSE_INGREDIENT_ANY: &str = "*";
+/// max tokens burned by a single recipe, keeps internal_fuse within gas
+pub const MAX_FUSE_RECIPE_TOKENS: u32 = 20;
+/// max tokens minted by a single recipe
+pub const MAX_FUSE_RECIPE_OUTPUTS: u32 = 10;
+/// success_rate and rolls are in basis points
+pub const FUSE_RATE_DENOMINATOR: u16 = 10_000;
+
+/// Stored form of FuseRecipe, add a variant instead of changing an existing one
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub enum VersionedFuseRecipe {
+    V1(FuseRecipe),
+}
+
+impl From<FuseRecipe> for VersionedFuseRecipe {
+    fn from(recipe: FuseRecipe) -> Self {
+        VersionedFuseRecipe::V1(recipe)
+    }
+}
+
+impl From<VersionedFuseRecipe> for FuseRecipe {
+    fn from(recipe: VersionedFuseRecipe) -> Self {
+        match recipe {
+            VersionedFuseRecipe::V1(recipe) => recipe,
+        }
+    }
+}
+
+/// Every ingredient must be filled and every token must fill exactly one ingredient
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseRecipe {
+    pub ingredients: Vec<FuseIngredient>,
+    /// chance of success in basis points, None always succeeds
+    pub success_rate: Option<u16>,
+    /// what happens to the tokens when the roll fails, defaults to BurnAll
+    pub on_failure: Option<FuseFailure>,
+    /// tokens minted on success, None for one token of the target series
+    pub outputs: Option<Vec<FuseOutput>>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseOutput {
+    pub token_series_id: TokenSeriesId,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FuseFailure {
+    BurnAll,
+    /// burns only the tokens that fill these ingredients, the rest stay with the owner
+    BurnIngredients { ingredient_indexes: Vec<u32> },
+    /// burns all tokens and mints one token of token_series_id
+    Consolation { token_series_id: TokenSeriesId },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseIngredient {
+    /// tokens from any of these series, None for any series
+    pub token_series_ids: Option<Vec<TokenSeriesId>>,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    /// checked against the series metadata of the token
+    pub predicate: Option<MetadataPredicate>,
+}
+
+/// metadata.extra is a JSON object and its `key` field equals `value`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataPredicate {
+    pub key: String,
+    pub value: String,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+impl FuseRecipe {
+    /// one token from each series, the same series can be repeated
+    pub fn from_token_series_ids(token_series_ids: Vec<TokenSeriesId>) -> Self {
+        Self {
+            ingredients: token_series_ids
+                .into_iter()
+                .map(|token_series_id| FuseIngredient {
+                    token_series_ids: Some(vec![token_series_id]),
+                    quantity: 1,
+                    predicate: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn assert_valid(&self) {
+        assert!(
+            !self.ingredients.is_empty(),
+            "Skins: fuse recipe has no ingredients"
+        );
+        let mut total_quantity: u32 = 0;
+        for ingredient in self.ingredients.iter() {
+            assert!(ingredient.quantity > 0, "Skins: ingredient quantity is 0");
+            if let Some(token_series_ids) = &ingredient.token_series_ids {
+                assert!(
+                    !token_series_ids.is_empty(),
+                    "Skins: ingredient token_series_ids is empty"
+                );
+            }
+            total_quantity = total_quantity.saturating_add(ingredient.quantity);
+        }
+        assert!(
+            total_quantity <= MAX_FUSE_RECIPE_TOKENS,
+            "Skins: fuse recipe exceeds {} tokens",
+            MAX_FUSE_RECIPE_TOKENS
+        );
+
+        if let Some(success_rate) = self.success_rate {
+            assert!(
+                success_rate <= FUSE_RATE_DENOMINATOR,
+                "Skins: success_rate exceeds {}",
+                FUSE_RATE_DENOMINATOR
+            );
+        }
+        if let Some(outputs) = &self.outputs {
+            assert!(!outputs.is_empty(), "Skins: fuse recipe outputs is empty");
+            let mut total_outputs: u32 = 0;
+            for output in outputs.iter() {
+                assert!(output.quantity > 0, "Skins: output quantity is 0");
+                total_outputs = total_outputs.saturating_add(output.quantity);
+            }
+            assert!(
+                total_outputs <= MAX_FUSE_RECIPE_OUTPUTS,
+                "Skins: fuse recipe exceeds {} outputs",
+                MAX_FUSE_RECIPE_OUTPUTS
+            );
+        }
+        if let Some(FuseFailure::BurnIngredients { ingredient_indexes }) = &self.on_failure {
+            for ingredient_idx in ingredient_indexes.iter() {
+                assert!(
+                    (*ingredient_idx as usize) < self.ingredients.len(),
+                    "Skins: on_failure ingredient index {} not exist",
+                    ingredient_idx
+                );
+            }
+        }
+    }
+
+    /// roll is in 0..FUSE_RATE_DENOMINATOR
+    pub fn is_success(&self, roll: u16) -> bool {
+        self.success_rate
+            .map(|success_rate| roll < success_rate)
+            .unwrap_or(true)
+    }
+
+    /// tokens minted on success when fusing into target_token_series_id
+    pub fn outputs_for(&self, target_token_series_id: &TokenSeriesId) -> Vec<FuseOutput> {
+        self.outputs.clone().unwrap_or_else(|| {
+            vec![FuseOutput {
+                token_series_id: target_token_series_id.clone(),
+                quantity: 1,
+            }]
+        })
+    }
+
+    /// index of the ingredient for each token the recipe burns
+    // repeat_n needs a newer toolchain than the contract is built with
+    #[allow(unknown_lints, clippy::manual_repeat_n)]
+    pub fn slots(&self) -> Vec<usize> {
+        self.ingredients
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, ingredient)| std::iter::repeat(idx).take(ingredient.quantity as usize))
+            .collect()
+    }
+
+    /// Assigns tokens (series id and series metadata) to slots by maximum bipartite matching.
+    /// Returns the token index assigned to each slot, None when the slot can't be filled
+    pub fn assign(&self, tokens: &[(&str, &TokenMetadata)]) -> Vec<Option<usize>> {
+        let slots = self.slots();
+        let edges: Vec<Vec<usize>> = slots
+            .iter()
+            .map(|idx| {
+                let ingredient = &self.ingredients[*idx];
+                tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (token_series_id, metadata))| {
+                        ingredient.accepts(token_series_id, metadata)
+                    })
+                    .map(|(token_idx, _)| token_idx)
+                    .collect()
+            })
+            .collect();
+
+        let mut slot_by_token: Vec<Option<usize>> = vec![None; tokens.len()];
+        for slot in 0..slots.len() {
+            let mut visited = vec![false; tokens.len()];
+            try_assign(slot, &edges, &mut visited, &mut slot_by_token);
+        }
+
+        let mut token_by_slot: Vec<Option<usize>> = vec![None; slots.len()];
+        for (token_idx, slot) in slot_by_token.iter().enumerate() {
+            if let Some(slot) = slot {
+                token_by_slot[*slot] = Some(token_idx);
+            }
+        }
+        token_by_slot
+    }
+
+    /// unique series ids used by ingredients, FUSE_INGREDIENT_ANY for wildcard ingredients
+    pub fn ingredient_series_ids(&self) -> Vec<TokenSeriesId> {
+        let mut ingredients: Vec<TokenSeriesId> = vec![];
+        for ingredient in self.ingredients.iter() {
+            let token_series_ids = match &ingredient.token_series_ids {
+                Some(token_series_ids) => token_series_ids.clone(),
+                None => vec![FUSE_INGREDIENT_ANY.to_string()],
+            };
+            for token_series_id in token_series_ids {
+                if !ingredients.contains(&token_series_id) {
+                    ingredients.push(token_series_id);
+                }
+            }
+        }
+        ingredients
+    }
+}
+
+fn try_assign(
+    slot: usize,
+    edges: &[Vec<usize>],
+    visited: &mut [bool],
+    slot_by_token: &mut [Option<usize>],
+) -> bool {
+    for &token_idx in edges[slot].iter() {
+        if visited[token_idx] {
+            continue;
+        }
+        visited[token_idx] = true;
+        let is_free = match slot_by_token[token_idx] {
+            None => true,
+            Some(other_slot) => try_assign(other_slot, edges, visited, slot_by_token),
+        };
+        if is_free {
+            slot_by_token[token_idx] = Some(slot);
+            return true;
+        }
+    }
+    false
+}
+
+impl FuseIngredient {
+    pub fn accepts(&self, token_series_id: &str, metadata: &TokenMetadata) -> bool {
+        let is_series_accepted = self
+            .token_series_ids
+            .as_ref()
+            .map(|token_series_ids| token_series_ids.iter().any(|id| id == token_series_id))
+            .unwrap_or(true);
+        is_series_accepted
+            && self
+                .predicate
+                .as_ref()
+                .map(|predicate| predicate.matches(metadata))
+                .unwrap_or(true)
+    }
+}
+
+impl Display for FuseIngredient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.token_series_ids {
+            Some(token_series_ids) => write!(f, "{} of series {:?}", self.quantity, token_series_ids)?,
+            None => write!(f, "{} of any series", self.quantity)?,
+        };
+        if let Some(predicate) = &self.predicate {
+            write!(f, " with extra.{}={}", predicate.key, predicate.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl MetadataPredicate {
+    pub fn matches(&self, metadata: &TokenMetadata) -> bool {
+        let extra: Value = match metadata
+            .extra
+            .as_ref()
+            .and_then(|extra| near_sdk::serde_json::from_str(extra).ok())
+        {
+            Some(extra) => extra,
+            None => return false,
+        };
+        match extra.get(&self.key) {
+            Some(Value::String(value)) => *value == self.value,
+            // numbers and bools match their JSON text, e.g. "5" or "true"
+            Some(value) => self.value == format!("{}", value),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(extra: Option<&str>) -> TokenMetadata {
+        TokenMetadata {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: extra.map(|extra| extra.to_string()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    fn ingredient(
+        token_series_ids: Option<Vec<&str>>,
+        quantity: u32,
+        predicate: Option<(&str, &str)>,
+    ) -> FuseIngredient {
+        FuseIngredient {
+            token_series_ids: token_series_ids
+                .map(|ids| ids.iter().map(|id| id.to_string()).collect()),
+            quantity,
+            predicate: predicate.map(|(key, value)| MetadataPredicate {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    fn is_filled(assignment: &[Option<usize>]) -> bool {
+        assignment.iter().all(|token_idx| token_idx.is_some())
+    }
+
+    #[test]
+    fn assign_quantity() {
+        let recipe = FuseRecipe {
+            ingredients: vec![ingredient(Some(vec!["2"]), 3, None)],
+            ..Default::default()
+        };
+        let md = metadata(None);
+
+        assert!(is_filled(&recipe.assign(&[("2", &md), ("2", &md), ("2", &md)])));
+        assert!(!is_filled(&recipe.assign(&[("2", &md), ("2", &md), ("1", &md)])));
+    }
+
+    #[test]
+    fn assign_wildcard_after_exact() {
+        // the "1" token must go to the exact ingredient even if it is listed first
+        let recipe = FuseRecipe {
+            ingredients: vec![
+                ingredient(Some(vec!["1", "2"]), 1, None),
+                ingredient(Some(vec!["1"]), 1, None),
+            ],
+            ..Default::default()
+        };
+        let md = metadata(None);
+
+        assert_eq!(recipe.assign(&[("1", &md), ("2", &md)]), vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn assign_predicate() {
+        let recipe = FuseRecipe {
+            ingredients: vec![ingredient(None, 1, Some(("rarity", "epic")))],
+            ..Default::default()
+        };
+        let epic = metadata(Some(r#"{"rarity":"epic"}"#));
+        let common = metadata(Some(r#"{"rarity":"common"}"#));
+        let no_extra = metadata(None);
+
+        assert!(is_filled(&recipe.assign(&[("7", &epic)])));
+        assert!(!is_filled(&recipe.assign(&[("7", &common)])));
+        assert!(!is_filled(&recipe.assign(&[("7", &no_extra)])));
+    }
+
+    #[test]
+    fn assign_predicate_non_string() {
+        let recipe = FuseRecipe {
+            ingredients: vec![
+                ingredient(None, 1, Some(("level", "5"))),
+                ingredient(None, 1, Some(("shiny", "true"))),
+            ],
+            ..Default::default()
+        };
+        let level_5 = metadata(Some(r#"{"level":5}"#));
+        let level_6 = metadata(Some(r#"{"level":6}"#));
+        let shiny = metadata(Some(r#"{"shiny":true}"#));
+
+        assert!(is_filled(&recipe.assign(&[("7", &level_5), ("8", &shiny)])));
+        assert!(!is_filled(&recipe.assign(&[("7", &level_6), ("8", &shiny)])));
+    }
+
+    #[test]
+    fn recipe_json() {
+        let recipe: FuseRecipe = near_sdk::serde_json::from_str(
+            r#"{"ingredients":[{"token_series_ids":["2"],"quantity":3},{"predicate":{"key":"rarity","value":"epic"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            recipe,
+            FuseRecipe {
+                ingredients: vec![
+                    ingredient(Some(vec!["2"]), 3, None),
+                    ingredient(None, 1, Some(("rarity", "epic"))),
+                ],
+                ..Default::default()
+            }
+        );
+        assert_eq!(recipe.ingredient_series_ids(), vec!["2", FUSE_INGREDIENT_ANY]);
+    }
+
+    #[test]
+    fn recipe_on_failure_json() {
+        let recipe: FuseRecipe = near_sdk::serde_json::from_str(
+            r#"{"ingredients":[{"token_series_ids":["2"]}],"success_rate":2500,"on_failure":{"type":"consolation","token_series_id":"9"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            recipe.on_failure,
+            Some(FuseFailure::Consolation {
+                token_series_id: "9".to_string()
+            })
+        );
+        assert!(recipe.is_success(2499));
+        assert!(!recipe.is_success(2500));
+        assert!(FuseRecipe::default().is_success(FUSE_RATE_DENOMINATOR - 1));
+    }
+
+    #[test]
+    fn recipe_outputs() {
+        let recipe: FuseRecipe = near_sdk::serde_json::from_str(
+            r#"{"ingredients":[{"token_series_ids":["2"],"quantity":3}],"outputs":[{"token_series_id":"5"},{"token_series_id":"6","quantity":2}]}"#,
+        )
+        .unwrap();
+        recipe.assert_valid();
+        let outputs = recipe.outputs_for(&"9".to_string());
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].quantity, 2);
+
+        let outputs = FuseRecipe::default().outputs_for(&"9".to_string());
+        assert_eq!(
+            outputs,
+            vec![FuseOutput {
+                token_series_id: "9".to_string(),
+                quantity: 1
+            }]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+/// fuse of a workbench with a success_rate paid at commit, rolled at reveal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseRequest {
+    /// sha256 of the salt given at reveal
+    pub salt_hash: Base64VecU8,
+    /// NEAR when None
+    pub ft_contract_id: Option<AccountId>,
+    pub price: U128,
+    pub block_index: U64,
+}
+
+impl FuseRequest {
+    pub(crate) fn is_expired(&self) -> bool {
+        env::block_index() > self.block_index.0 + REQUEST_EXPIRY_BLOCKS
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pays the NEAR fuse cost of the workbench for target_token_series_id, the fuse is rolled by
+    /// nft_reveal_fuse in a later block. The workbench is locked until then
+    #[payable]
+    pub fn nft_commit_fuse(&mut self, target_token_series_id: TokenSeriesId, salt_hash: Base64VecU8) -> FuseRequest {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let (_, fuse_price) = self.internal_assert_near_fuse_payment(&target_token_series_id);
+
+        let fuse_request = FuseRequest {
+            salt_hash,
+            ft_contract_id: None,
+            price: U128(fuse_price),
+            block_index: U64(env::block_index()),
+        };
+        self.internal_commit_fuse(&account_id, target_token_series_id, fuse_request.clone())
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, fuse_price);
+        fuse_request
+    }
+
+    /// Fuses the workbench of the caller for target_token_series_id, attach the storage of the outputs
+    #[payable]
+    pub fn nft_reveal_fuse(&mut self, target_token_series_id: TokenSeriesId, salt: String) -> FuseOutcome {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+
+        let fuse_outcome = self
+            .internal_reveal_fuse(&account_id, target_token_series_id, &salt)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        // the burned tokens can free more storage than the minted ones take
+        refund_deposit(
+            env::storage_usage().saturating_sub(initial_storage_usage),
+            0,
+        );
+        fuse_outcome
+    }
+
+    /// Gives back the fuse cost of an expired fuse request, the tokens stay on the workbench
+    #[payable]
+    pub fn nft_refund_fuse(&mut self, target_token_series_id: TokenSeriesId) {
+        assert_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+
+        let key = (account_id.clone(), target_token_series_id.clone());
+        let fuse_request = self
+            .fuse_requests
+            .get(&key)
+            .expect("Skins: fuse request not exist");
+        assert!(fuse_request.is_expired(), "Skins: fuse request not expired");
+        self.fuse_requests.remove(&key);
+
+        if fuse_request.price.0 > 0 {
+            match &fuse_request.ft_contract_id {
+                Some(ft_contract_id) => {
+                    self.internal_send_fuse_fees(
+                        ft_contract_id.clone(),
+                        account_id.clone(),
+                        fuse_request.price.0,
+                        "Skins: fuse request refund",
+                    );
+                }
+                None => {
+                    Promise::new(account_id.clone()).transfer(fuse_request.price.0);
+                }
+            }
+        }
+        refund_freed_storage(&account_id, initial_storage_usage);
+
+        env::log(
+            json!({
+                "type": "nft_refund_fuse",
+                "params": {
+                    "account_id": account_id,
+                    "target_token_series_id": target_token_series_id,
+                    "fuse_request": fuse_request,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    pub fn nft_get_fuse_request(
+        &self,
+        account_id: ValidAccountId,
+        target_token_series_id: TokenSeriesId,
+    ) -> Option<FuseRequest> {
+        self.fuse_requests.get(&(account_id.into(), target_token_series_id))
+    }
+}
+
+impl Contract {
+    /// Records fuse_request when the workbench matches a fuse_requirement with a success_rate,
+    /// returns Err before any state change
+    pub(crate) fn internal_commit_fuse(
+        &mut self,
+        account_id: &AccountId,
+        target_token_series_id: TokenSeriesId,
+        fuse_request: FuseRequest,
+    ) -> Result<(), String> {
+        let key = (account_id.clone(), target_token_series_id.clone());
+        self.internal_check_no_fuse_request(account_id, &target_token_series_id)?;
+
+        let fuse_requirements = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .and_then(|token_series| to_fuse_recipes(token_series.fuse_requirements))
+            .ok_or_else(|| "Skins: Token series is not fusable".to_string())?;
+        let workbench = self.internal_workbench(account_id, &target_token_series_id);
+        let (fuse_requirement_index, _) = self.internal_match_fuse_requirement(
+            &workbench,
+            &fuse_requirements,
+            &env::current_account_id(),
+        )?;
+        if fuse_requirements[fuse_requirement_index].success_rate.is_none() {
+            return Err("Skins: fuse_requirement can't fail, use nft_fuse_workbench_finalize".to_string());
+        }
+
+        self.fuse_requests.insert(&key, &fuse_request);
+
+        env::log(
+            json!({
+                "type": "nft_commit_fuse",
+                "params": {
+                    "account_id": account_id,
+                    "target_token_series_id": target_token_series_id,
+                    "fuse_request": fuse_request,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        Ok(())
+    }
+
+    /// Rolls and fuses the workbench of the fuse request, returns Err before any state change
+    pub(crate) fn internal_reveal_fuse(
+        &mut self,
+        account_id: &AccountId,
+        target_token_series_id: TokenSeriesId,
+        salt: &str,
+    ) -> Result<FuseOutcome, String> {
+        let key = (account_id.clone(), target_token_series_id.clone());
+        let fuse_request = self
+            .fuse_requests
+            .get(&key)
+            .ok_or_else(|| "Skins: fuse request not exist".to_string())?;
+        if env::block_index() <= fuse_request.block_index.0 {
+            return Err("Skins: fuse request can be revealed from the next block".to_string());
+        }
+        if fuse_request.is_expired() {
+            return Err("Skins: fuse request expired".to_string());
+        }
+        if env::sha256(salt.as_bytes()) != fuse_request.salt_hash.0 {
+            return Err("Skins: salt does not match salt_hash".to_string());
+        }
+        let creator_id = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?
+            .creator_id;
+
+        // the commit block mixes in like the loot request id
+        let random_seed = reveal_seed(&env::random_seed(), salt, fuse_request.block_index.0);
+        let workbench = self.internal_workbench(account_id, &target_token_series_id);
+        let fuse_outcome = self.internal_fuse_workbench(
+            account_id,
+            target_token_series_id,
+            workbench,
+            Some(&random_seed),
+        )?;
+        self.fuse_requests.remove(&key);
+
+        let price = fuse_request.price.0;
+        match &fuse_request.ft_contract_id {
+            Some(ft_contract_id) => self.internal_collect_fuse_fee(ft_contract_id, &creator_id, price),
+            None => self.internal_pay_near_fuse_fee(&creator_id, price),
+        }
+        Ok(fuse_outcome)
+    }
+
+    /// the workbench of account_id for target_token_series_id is not locked by a fuse request
+    pub(crate) fn internal_check_no_fuse_request(
+        &self,
+        account_id: &AccountId,
+        target_token_series_id: &TokenSeriesId,
+    ) -> Result<(), String> {
+        if self
+            .fuse_requests
+            .contains_key(&(account_id.clone(), target_token_series_id.clone()))
+        {
+            return Err("Skins: fuse request pending for the workbench".to_string());
+        }
+        Ok(())
+    }
+}
@@ -7,7 +7,7 @@ use near_contract_standards::non_fungible_token::metadata::{
 use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet};
 use near_sdk::env::is_valid_account_id;
 use near_sdk::json_types::{ValidAccountId, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
@@ -19,7 +19,30 @@ use std::collections::{HashMap};
 
 pub mod event;
 pub use event::NearEvent;
+pub mod fuse_recipe;
+pub use fuse_recipe::{
+    FuseFailure, FuseOutput, FuseRecipe, VersionedFuseRecipe, FUSE_INGREDIENT_ANY,
+    FUSE_RATE_DENOMINATOR,
+};
 pub mod token_receiver;
+pub mod nft_receiver;
+pub mod workbench;
+pub mod loot;
+pub use loot::{
+    assert_valid_loot_table, roll_loot_table, LootBox, LootBoxId, LootBoxJson, LootBoxKey, LootEntry,
+    LootOdds, LootPity, LOOT_WEIGHT_DENOMINATOR,
+};
+pub mod loot_request;
+pub use loot_request::{LootRequest, LootRequestId};
+pub mod commit_reveal;
+pub use commit_reveal::{reveal_seed, REQUEST_EXPIRY_BLOCKS};
+pub mod fuse_fee;
+pub mod fuse_request;
+pub use fuse_request::FuseRequest;
+pub mod migrate;
+pub mod sale;
+pub mod storage;
+pub use sale::{FtPrice, PriceCurve, SaleConfig, SalePhase, SaleStatus};
 
 /// between token_series_id and edition number e.g. 42:2 where 42 is series and 2 is edition
 pub const TOKEN_DELIMETER: char = ':';
@@ -28,14 +51,20 @@ pub const TITLE_DELIMETER: &str = " #";
 /// e.g. "Title — 2/10" where 10 is max copies
 pub const EDITION_DELIMETER: &str = "/";
 pub const TREASURY_FEE: u128 = 500; // 500 / 10_000 = 0.05
+/// fuse_cost token id for costs paid in native NEAR
+pub const NEAR_TOKEN_ID: &str = "near";
 
 const GAS_FOR_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
 const GAS_FOR_NFT_TRANSFER_CALL: Gas = 30_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER;
 const GAS_FOR_NFT_APPROVE: Gas = 10_000_000_000_000;
 const GAS_FOR_MINT: Gas = 90_000_000_000_000;
 const NO_DEPOSIT: Balance = 0;
+/// default limit of tokens minted by a single nft_buy_many
+const DEFAULT_MAX_BUY_QUANTITY: u32 = 10;
 
 pub type TokenSeriesId = String;
+/// fuse price by NEAR_TOKEN_ID or approved FT contract
+pub type FuseCost = HashMap<AccountId, U128>;
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -85,8 +114,22 @@ pub struct TokenSeries {
     price: Option<Balance>,
     is_mintable: bool,
     royalty: HashMap<AccountId, u32>,
-    fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
-    fuse_cost: Option<(AccountId, U128)>,
+    fuse_requirements: Option<Vec<VersionedFuseRecipe>>,
+    fuse_cost: Option<FuseCost>,
+    /// treasury share of primary sales in basis points, TREASURY_FEE when None
+    treasury_fee: Option<u32>,
+    sale_config: Option<SaleConfig>,
+    /// nanosecond timestamps bounding nft_buy
+    sale_starts_at: Option<u64>,
+    sale_ends_at: Option<u64>,
+    price_curve: Option<PriceCurve>,
+    ft_price: Option<FtPrice>,
+}
+
+impl TokenSeries {
+    fn treasury_fee(&self) -> u32 {
+        self.treasury_fee.unwrap_or(TREASURY_FEE as u32)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -96,8 +139,40 @@ pub struct TokenSeriesJson {
     metadata: TokenMetadata,
     creator_id: AccountId,
     royalty: HashMap<AccountId, u32>,
-    fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
-    fuse_cost: Option<(AccountId, U128)>,
+    fuse_requirements: Option<Vec<FuseRecipe>>,
+    fuse_cost: Option<FuseCost>,
+    treasury_fee: u32,
+    sale_config: Option<SaleConfig>,
+    sale_starts_at: Option<U64>,
+    sale_ends_at: Option<U64>,
+    sale_status: SaleStatus,
+    price_curve: Option<PriceCurve>,
+    ft_price: Option<FtPrice>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FusePreview {
+    /// index in fuse_requirements that token_ids fulfill
+    fuse_requirement_index: Option<u64>,
+    burned_token_ids: Vec<TokenId>,
+    fuse_cost: Option<FuseCost>,
+    /// chance of success of the matched fuse_requirement in basis points
+    success_rate: Option<u16>,
+    has_supply: bool,
+    /// why the fuse would fail
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseOutcome {
+    is_success: bool,
+    /// roll against success_rate, None when the fuse_requirement can't fail
+    roll: Option<u16>,
+    /// outputs on success, consolation token on failure
+    token_ids: Vec<TokenId>,
+    burned_token_ids: Vec<TokenId>,
 }
 
 near_sdk::setup_alloc!();
@@ -109,8 +184,35 @@ pub struct Contract {
     metadata: LazyOption<NFTContractMetadata>,
     // CUSTOM
     token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeries>,
-    random_loot: Option<Vec<TokenSeriesId>>,
-    last_token_by_owner: LookupMap<AccountId, TokenId>
+    /// tokens minted by the last fuse of the owner
+    last_tokens_by_owner: LookupMap<AccountId, Vec<TokenId>>,
+    fuse_targets_by_ingredient: LookupMap<TokenSeriesId, UnorderedSet<TokenSeriesId>>,
+    approved_ft_token_ids: UnorderedSet<AccountId>,
+    treasury_id: AccountId,
+    /// basis points of each fuse fee kept by the treasury
+    fuse_treasury_fee: u32,
+    /// collected fuse fees by (ft_contract_id, account_id)
+    fuse_fees: LookupMap<(AccountId, AccountId), Balance>,
+    max_buy_quantity: u32,
+    sale_allowlist: LookupSet<(TokenSeriesId, AccountId)>,
+    /// tokens bought or looted by (token_series_id, account_id)
+    purchases_by_account: LookupMap<(TokenSeriesId, AccountId), u32>,
+    /// FT paid for series and loot boxes by (ft_contract_id, account_id)
+    sale_proceeds: LookupMap<(AccountId, AccountId), Balance>,
+    /// NEAR deposited for the storage of FT actions and nft_transfer_call
+    storage_deposits: LookupMap<AccountId, Balance>,
+    /// tokens held by the contract for a fuse, by account_id then target_token_series_id
+    fuse_workbenches: LookupMap<AccountId, HashMap<TokenSeriesId, Vec<TokenId>>>,
+    loot_boxes: UnorderedMap<LootBoxId, LootBox>,
+    /// openings by (loot_box_id, account_id)
+    loot_box_openings: LookupMap<(LootBoxId, AccountId), u32>,
+    /// loot box openings waiting for their reveal
+    loot_requests: LookupMap<LootRequestId, LootRequest>,
+    next_loot_request_id: LootRequestId,
+    /// pending chance fuses by (account_id, target_token_series_id)
+    fuse_requests: LookupMap<(AccountId, TokenSeriesId), FuseRequest>,
+    /// openings without the pity token series by (loot_box_id, account_id)
+    loot_pity_counters: LookupMap<(LootBoxId, AccountId), u32>,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -127,6 +229,21 @@ enum StorageKey {
     TokensBySeriesInner { token_series: String },
     TokensPerOwner { account_hash: Vec<u8> },
     LastTokenByOwner,
+    FuseTargetsByIngredient,
+    FuseTargetsByIngredientInner { token_series_id: String },
+    LastTokensByOwner,
+    ApprovedFtTokenIds,
+    FuseFees,
+    SaleAllowlist,
+    PurchasesByAccount,
+    SaleProceeds,
+    StorageDeposits,
+    FuseWorkbenches,
+    LootBoxes,
+    LootBoxOpenings,
+    LootRequests,
+    FuseRequests,
+    LootPityCounters,
 }
 
 #[near_bindgen]
@@ -151,6 +268,7 @@ impl Contract {
     pub fn new(owner_id: ValidAccountId, metadata: NFTContractMetadata) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
+        let treasury_id: AccountId = owner_id.clone().into();
         Self {
             tokens: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
@@ -161,8 +279,24 @@ impl Contract {
             ),
             token_series_by_id: UnorderedMap::new(StorageKey::TokenSeriesById),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
-            random_loot: None,
-            last_token_by_owner: LookupMap::new(StorageKey::LastTokenByOwner)
+            last_tokens_by_owner: LookupMap::new(StorageKey::LastTokensByOwner),
+            fuse_targets_by_ingredient: LookupMap::new(StorageKey::FuseTargetsByIngredient),
+            approved_ft_token_ids: UnorderedSet::new(StorageKey::ApprovedFtTokenIds),
+            treasury_id,
+            fuse_treasury_fee: TREASURY_FEE as u32,
+            fuse_fees: LookupMap::new(StorageKey::FuseFees),
+            max_buy_quantity: DEFAULT_MAX_BUY_QUANTITY,
+            sale_allowlist: LookupSet::new(StorageKey::SaleAllowlist),
+            purchases_by_account: LookupMap::new(StorageKey::PurchasesByAccount),
+            sale_proceeds: LookupMap::new(StorageKey::SaleProceeds),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            fuse_workbenches: LookupMap::new(StorageKey::FuseWorkbenches),
+            loot_boxes: UnorderedMap::new(StorageKey::LootBoxes),
+            loot_box_openings: LookupMap::new(StorageKey::LootBoxOpenings),
+            loot_requests: LookupMap::new(StorageKey::LootRequests),
+            next_loot_request_id: 0,
+            fuse_requests: LookupMap::new(StorageKey::FuseRequests),
+            loot_pity_counters: LookupMap::new(StorageKey::LootPityCounters),
         }
     }
 
@@ -175,8 +309,8 @@ impl Contract {
         token_metadata: TokenMetadata,
         price: Option<U128>,
         royalty: Option<HashMap<AccountId, u32>>,
-        fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
-        fuse_cost: Option<(AccountId, U128)>,
+        fuse_requirements: Option<Vec<FuseRecipe>>,
+        fuse_cost: Option<FuseCost>,
     ) -> TokenSeriesJson {
         assert_eq!(
             env::predecessor_account_id(),
@@ -186,6 +320,8 @@ impl Contract {
 
         let initial_storage_usage = env::storage_usage();
         let caller_id = env::predecessor_account_id();
+        self.internal_assert_fuse_cost(&fuse_cost);
+        self.internal_assert_fuse_requirements(&fuse_requirements);
 
         if creator_id.is_some() {
             assert_eq!(
@@ -233,25 +369,30 @@ impl Contract {
             None
         };
 
-        self.token_series_by_id.insert(
-            &token_series_id,
-            &TokenSeries {
-                metadata: token_metadata.clone(),
-                creator_id: caller_id.to_string(),
-                tokens: UnorderedSet::new(
-                    StorageKey::TokensBySeriesInner {
-                        token_series: token_series_id.clone(),
-                    }
-                    .try_to_vec()
-                    .unwrap(),
-                ),
-                price: price_res,
-                is_mintable: true,
-                royalty: royalty_res.clone(),
-                fuse_requirements: fuse_requirements.clone(),
-                fuse_cost: fuse_cost.clone(),
-            },
-        );
+        let token_series = TokenSeries {
+            metadata: token_metadata.clone(),
+            creator_id: caller_id.to_string(),
+            tokens: UnorderedSet::new(
+                StorageKey::TokensBySeriesInner {
+                    token_series: token_series_id.clone(),
+                }
+                .try_to_vec()
+                .unwrap(),
+            ),
+            price: price_res,
+            is_mintable: true,
+            royalty: royalty_res.clone(),
+            fuse_requirements: to_versioned_fuse_recipes(fuse_requirements.clone()),
+            fuse_cost: fuse_cost.clone(),
+            treasury_fee: None,
+            sale_config: None,
+            sale_starts_at: None,
+            sale_ends_at: None,
+            price_curve: None,
+            ft_price: None,
+        };
+        self.token_series_by_id.insert(&token_series_id, &token_series);
+        self.internal_add_fuse_targets(&token_series_id, &fuse_requirements);
 
         env::log(
             json!({
@@ -277,6 +418,13 @@ impl Contract {
             royalty: royalty_res,
             fuse_requirements,
             fuse_cost,
+            treasury_fee: token_series.treasury_fee(),
+            sale_config: None,
+            sale_starts_at: None,
+            sale_ends_at: None,
+            sale_status: token_series.sale_status(),
+            price_curve: None,
+            ft_price: None,
         }
     }
 
@@ -286,28 +434,120 @@ impl Contract {
         token_series_id: TokenSeriesId,
         receiver_id: ValidAccountId,
     ) -> TokenId {
+        self.internal_buy(token_series_id, receiver_id.into(), 1)
+            .pop()
+            .unwrap()
+    }
+
+    /// Mints quantity tokens of token_series_id to receiver_id for quantity * price plus storage
+    #[payable]
+    pub fn nft_buy_many(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        receiver_id: ValidAccountId,
+        quantity: u32,
+    ) -> Vec<TokenId> {
+        assert!(quantity > 0, "Skins: quantity must be at least 1");
+        assert!(
+            quantity <= self.max_buy_quantity,
+            "Skins: quantity exceeds max_buy_quantity {}",
+            self.max_buy_quantity
+        );
+        self.internal_buy(token_series_id, receiver_id.into(), quantity)
+    }
+
+    pub fn set_max_buy_quantity(&mut self, max_buy_quantity: u32) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        assert!(max_buy_quantity > 0, "Skins: max_buy_quantity must be at least 1");
+        self.max_buy_quantity = max_buy_quantity;
+    }
+
+    pub fn get_max_buy_quantity(&self) -> u32 {
+        self.max_buy_quantity
+    }
+
+    fn internal_buy(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        receiver_id: AccountId,
+        quantity: u32,
+    ) -> Vec<TokenId> {
+        let initial_storage_usage = env::storage_usage();
+
         let token_series = self
             .token_series_by_id
             .get(&token_series_id)
             .expect("Skins: Token series not exist");
-        let price: u128 = token_series.price.expect("Skins: not for sale");
+        match token_series.sale_status() {
+            SaleStatus::Upcoming => env::panic(b"Skins: sale not started"),
+            SaleStatus::Ended => env::panic(b"Skins: sale ended"),
+            _ => {}
+        }
+        // the allowlist price only discounts a series that is for sale in NEAR
+        let price = token_series.quote(quantity).expect("Skins: not for sale");
+        let buyer_id = env::predecessor_account_id();
+        let allowlist_sale_config = self
+            .internal_check_purchase(&token_series_id, &token_series, &buyer_id, quantity)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+        self.internal_record_purchase(&token_series_id, &buyer_id, quantity);
+        // never above the public quote, which a price_curve can bring below the allowlist price
+        let total_price: u128 = allowlist_sale_config
+            .and_then(|sale_config| sale_config.allowlist_price)
+            .map_or(price, |allowlist_price| (allowlist_price.0 * quantity as u128).min(price));
         let attached_deposit = env::attached_deposit();
         assert!(
-            attached_deposit >= price,
+            attached_deposit >= total_price,
             "Skins: attached deposit is less than price : {}",
-            price
+            total_price
+        );
+
+        if let Some(copies) = token_series.metadata.copies {
+            let remaining = copies.saturating_sub(token_series.tokens.len());
+            assert!(
+                quantity as u64 <= remaining,
+                "Skins: only {} tokens left in series",
+                remaining
+            );
+        }
+
+        let token_ids: Vec<TokenId> = (0..quantity)
+            .map(|_| self._nft_mint_series(token_series_id.clone(), receiver_id.clone()))
+            .collect();
+
+        let payout = self.internal_primary_sale_payout(&token_series, total_price);
+        for (account_id, amount) in payout.payout.iter() {
+            if amount.0 > 0 {
+                Promise::new(account_id.clone()).transfer(amount.0);
+            }
+        }
+
+        env::log(
+            json!({
+                "type": "nft_buy_payout",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "token_ids": token_ids,
+                    "price": U128(total_price),
+                    "payout": payout.payout,
+                }
+            })
+            .to_string()
+            .as_bytes(),
         );
-        let token_id: TokenId = self._nft_mint_series(token_series_id, receiver_id.to_string());
 
-        Promise::new(token_series.creator_id).transfer(price);
+        refund_deposit(env::storage_usage() - initial_storage_usage, total_price);
 
         NearEvent::log_nft_mint(
-            receiver_id.to_string(),
-            vec![token_id.clone()],
-            Some(json!({"price": price.to_string()}).to_string()),
+            receiver_id,
+            token_ids.clone(),
+            Some(json!({"price": total_price.to_string()}).to_string()),
         );
 
-        token_id
+        token_ids
     }
 
     #[payable]
@@ -336,92 +576,397 @@ impl Contract {
         token_id
     }
 
-    pub fn set_nft_random_loot(&mut self, token_series_ids: Vec<TokenSeriesId>) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.tokens.owner_id,
-            "Skins: Owner only"
-        );
+    #[payable]
+    pub fn nft_fuse(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        target_token_series_id: TokenSeriesId,
+        owner_id: Option<ValidAccountId>,
+    ) -> FuseOutcome {
+        let initial_storage_usage = env::storage_usage();
+        let caller_id = env::predecessor_account_id();
+        let owner_id: AccountId = owner_id.map(|a| a.into()).unwrap_or_else(|| caller_id.clone());
+
+        let (creator_id, fuse_price) = self.internal_assert_near_fuse_payment(&target_token_series_id);
+
+        if caller_id != owner_id {
+            let approvals_by_id = self.tokens.approvals_by_id.as_ref().unwrap();
+            for token_id in token_ids.iter() {
+                let is_approved = approvals_by_id
+                    .get(token_id)
+                    .map(|approved_account_ids| approved_account_ids.contains_key(&caller_id))
+                    .unwrap_or(false);
+                assert!(is_approved, "Skins: caller is not approved for {}", token_id);
+            }
+        }
+
+        let fuse_outcome = self
+            .internal_fuse(token_ids, target_token_series_id, &owner_id, owner_id.clone(), None)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+        self.internal_pay_near_fuse_fee(&creator_id, fuse_price);
 
-        assert_eq!(token_series_ids.len(), 2, "Skins: only support 2 token_series_id now");
+        // the burned tokens can free more storage than the minted ones take
+        refund_deposit(
+            env::storage_usage().saturating_sub(initial_storage_usage),
+            fuse_price,
+        );
 
-        self.random_loot = Some(token_series_ids);
+        fuse_outcome
     }
 
-    #[payable]
-    pub fn nft_random_loot(&mut self) -> TokenId {
-        let receiver_id= env::predecessor_account_id();
+    /// Checks the attached deposit covers the NEAR fuse cost of target_token_series_id,
+    /// returns the series creator and the cost
+    fn internal_assert_near_fuse_payment(&self, target_token_series_id: &TokenSeriesId) -> (AccountId, Balance) {
+        let attached_deposit = env::attached_deposit();
+        assert!(
+            attached_deposit >= 1,
+            "Skins: Requires attached deposit of at least 1 yoctoNEAR"
+        );
 
-        let random_loot = self.random_loot.as_ref().unwrap().clone();
-        let rand: u8 = *env::random_seed().get(0).unwrap();
-        let token_id= if rand < 128 {
-            self._nft_mint_series(random_loot[0].clone(), receiver_id.clone())
-        } else {
-            self._nft_mint_series(random_loot[1].clone(), receiver_id.clone())
+        let token_series = self
+            .token_series_by_id
+            .get(target_token_series_id)
+            .expect("Skins: Token series not exist");
+        assert!(
+            token_series.fuse_requirements.is_some(),
+            "Skins: Token series is not fusable"
+        );
+
+        // fuse paid in FT must go through ft_transfer_call
+        let fuse_price: Balance = match token_series.fuse_cost {
+            Some(fuse_cost) => {
+                fuse_cost
+                    .get(NEAR_TOKEN_ID)
+                    .expect("Skins: fuse cost must be paid through ft_transfer_call")
+                    .0
+            }
+            None => 0,
         };
-        NearEvent::log_nft_mint(receiver_id.to_string(), vec![token_id.clone()], None);
-        token_id
+        assert!(
+            attached_deposit >= fuse_price,
+            "Skins: attached deposit is less than fuse cost : {}",
+            fuse_price
+        );
+        (token_series.creator_id, fuse_price)
     }
 
-    // WARNING: NO GUARD
-    #[payable]
-    pub fn nft_fuse(
+    /// Burns tokens of owner_id and mints the outputs to receiver_id.
+    /// A fuse_requirement with a success_rate is rolled from random_seed, given by nft_reveal_fuse only.
+    /// Nothing is changed when an error is returned
+    fn internal_fuse(
         &mut self,
         token_ids: Vec<TokenId>,
         target_token_series_id: TokenSeriesId,
-    ) -> Option<TokenId> {
-        self.internal_fuse(token_ids, target_token_series_id, env::predecessor_account_id())
+        owner_id: &AccountId,
+        receiver_id: AccountId,
+        random_seed: Option<&[u8]>,
+    ) -> Result<FuseOutcome, String> {
+        let token_series: TokenSeries = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?;
+        let fuse_requirements = to_fuse_recipes(token_series.fuse_requirements)
+            .ok_or_else(|| "Skins: Token series is not fusable".to_string())?;
+        let (fuse_requirement_index, ingredient_by_token) =
+            self.internal_match_fuse_requirement(&token_ids, &fuse_requirements, owner_id)?;
+        let fuse_requirement = &fuse_requirements[fuse_requirement_index];
+
+        let outputs = fuse_requirement.outputs_for(&target_token_series_id);
+        let roll: Option<u16> = match (fuse_requirement.success_rate, random_seed) {
+            (None, _) => None,
+            (Some(_), Some(random_seed)) => Some(random_roll(random_seed)),
+            (Some(_), None) => {
+                return Err("Skins: fuse_requirement with a success_rate must go through nft_commit_fuse".to_string())
+            }
+        };
+        let on_failure = fuse_requirement.on_failure.clone().unwrap_or(FuseFailure::BurnAll);
+        // both outcomes are checked before the roll, a reveal failing after it could be retried
+        self.internal_check_supply(&outputs)?;
+        if let (Some(_), FuseFailure::Consolation {token_series_id}) = (roll, &on_failure) {
+            self.internal_check_supply(&[FuseOutput {
+                token_series_id: token_series_id.clone(),
+                quantity: 1,
+            }])?;
+        }
+        let is_success = roll
+            .map(|roll| fuse_requirement.is_success(roll))
+            .unwrap_or(true);
+
+        let (burned_token_ids, outputs): (Vec<TokenId>, Vec<FuseOutput>) =
+            if is_success {
+                (token_ids, outputs)
+            } else {
+                match on_failure {
+                    FuseFailure::BurnAll => (token_ids, vec![]),
+                    FuseFailure::BurnIngredients { ingredient_indexes } => (
+                        token_ids
+                            .into_iter()
+                            .zip(ingredient_by_token)
+                            .filter(|(_, ingredient_idx)| {
+                                ingredient_indexes.contains(&(*ingredient_idx as u32))
+                            })
+                            .map(|(token_id, _)| token_id)
+                            .collect(),
+                        vec![],
+                    ),
+                    FuseFailure::Consolation { token_series_id } => (
+                        token_ids,
+                        vec![FuseOutput {
+                            token_series_id,
+                            quantity: 1,
+                        }],
+                    ),
+                }
+            };
+
+        for token_id in burned_token_ids.iter() {
+            self._nft_burn(owner_id.to_string(), token_id.clone());
+        }
+        let mut minted_token_ids: Vec<TokenId> = vec![];
+        for output in outputs {
+            for _ in 0..output.quantity {
+                minted_token_ids.push(
+                    self._nft_mint_series(output.token_series_id.clone(), receiver_id.to_string()),
+                );
+            }
+        }
+        if !minted_token_ids.is_empty() {
+            NearEvent::log_nft_mint(receiver_id.to_string(), minted_token_ids.clone(), None);
+            self.last_tokens_by_owner.insert(&receiver_id, &minted_token_ids);
+        }
+
+        env::log(
+            json!({
+                "type": "nft_fuse",
+                "params": {
+                    "owner_id": receiver_id,
+                    "target_token_series_id": target_token_series_id,
+                    "fuse_requirement_index": fuse_requirement_index,
+                    "success_rate": fuse_requirement.success_rate,
+                    "roll": roll,
+                    "is_success": is_success,
+                    "burned_token_ids": burned_token_ids,
+                    "token_ids": minted_token_ids,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        Ok(FuseOutcome {
+            is_success,
+            roll,
+            token_ids: minted_token_ids,
+            burned_token_ids,
+        })
     }
 
-    fn internal_fuse(
-        &mut self,
+    /// every output series can still mint its quantity under metadata.copies
+    fn internal_check_supply(&self, outputs: &[FuseOutput]) -> Result<(), String> {
+        let mut quantity_by_series: HashMap<&TokenSeriesId, u64> = HashMap::new();
+        for output in outputs.iter() {
+            *quantity_by_series.entry(&output.token_series_id).or_insert(0) +=
+                output.quantity as u64;
+        }
+        for (token_series_id, quantity) in quantity_by_series {
+            let token_series = match self.token_series_by_id.get(token_series_id) {
+                Some(token_series) => token_series,
+                None => return Err(format!("Skins: Token series {} not exist", token_series_id)),
+            };
+            if !token_series.is_mintable {
+                return Err(format!(
+                    "Skins: Token series {} is not mintable",
+                    token_series_id
+                ));
+            }
+            let max_copies = token_series.metadata.copies.unwrap_or(u64::MAX);
+            if token_series.tokens.len().saturating_add(quantity) > max_copies {
+                return Err(format!(
+                    "Skins: Token series {} supply maxed",
+                    token_series_id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn nft_fuse_preview(
+        &self,
         token_ids: Vec<TokenId>,
         target_token_series_id: TokenSeriesId,
-        receiver_id: AccountId,
-    ) -> Option<TokenId> {
-        let token_series: TokenSeries = self
+        owner_id: Option<ValidAccountId>,
+    ) -> FusePreview {
+        let token_series = self
             .token_series_by_id
             .get(&target_token_series_id)
             .expect("Skins: Token series not exist");
-        let fuse_requirements = token_series.fuse_requirements.unwrap();
-        let mut requirement_fulfilled = false;
-        for mut fuse_requirement in fuse_requirements {
-            for token_id in token_ids.clone() {
-                assert_eq!(
-                    self.tokens.owner_by_id.get(&token_id).unwrap(),
-                    receiver_id.to_string(),
-                    "Skins: token_id is not owned by receiver_id"
-                );
-                let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
-                let token_series_id: TokenSeriesId = token_id_iter.next().unwrap().parse().unwrap();
-                for (pos, token_series_id_req) in fuse_requirement.iter().enumerate() {
-                    if token_series_id == *token_series_id_req {
-                        fuse_requirement.remove(pos);
-                        break;
-                    }
+        let has_supply = token_series.is_mintable
+            && token_series.tokens.len() < token_series.metadata.copies.unwrap_or(u64::MAX);
+
+        let owner_id: Option<AccountId> = owner_id.map(|a| a.into()).or_else(|| {
+            token_ids
+                .first()
+                .and_then(|token_id| self.tokens.owner_by_id.get(token_id))
+        });
+
+        let fuse_requirements = to_fuse_recipes(token_series.fuse_requirements);
+        let result = match (&fuse_requirements, &owner_id) {
+            (None, _) => Err("Skins: Token series is not fusable".to_string()),
+            (_, None) => Err("Skins: token_ids is empty or not exist".to_string()),
+            (Some(fuse_requirements), Some(owner_id)) => {
+                self.internal_match_fuse_requirement(&token_ids, fuse_requirements, owner_id)
+            }
+        };
+
+        match result {
+            Ok((idx, _)) => {
+                let fuse_requirement = &fuse_requirements.as_ref().unwrap()[idx];
+                let supply = self
+                    .internal_check_supply(&fuse_requirement.outputs_for(&target_token_series_id));
+                FusePreview {
+                    fuse_requirement_index: Some(idx as u64),
+                    burned_token_ids: token_ids,
+                    fuse_cost: token_series.fuse_cost,
+                    success_rate: fuse_requirement.success_rate,
+                    has_supply: supply.is_ok(),
+                    error: supply.err(),
                 }
             }
-            if fuse_requirement.is_empty() {
-                requirement_fulfilled = true;
-                break
-            };
+            Err(err) => FusePreview {
+                fuse_requirement_index: None,
+                burned_token_ids: vec![],
+                fuse_cost: token_series.fuse_cost,
+                success_rate: None,
+                has_supply,
+                error: Some(err),
+            },
+        }
+    }
+
+    /// Every token in token_ids must be owned by owner_id and fill exactly one ingredient
+    /// of a fuse_requirement, every ingredient must be filled.
+    /// Returns the index of the matched fuse_requirement with the ingredient index of each token
+    /// or why none matched
+    fn internal_match_fuse_requirement(
+        &self,
+        token_ids: &[TokenId],
+        fuse_requirements: &[FuseRecipe],
+        owner_id: &AccountId,
+    ) -> Result<(usize, Vec<usize>), String> {
+        if token_ids.is_empty() {
+            return Err("Skins: token_ids is empty".to_string());
         }
-        if requirement_fulfilled {
-            for token_id in token_ids {
-                self._nft_burn(receiver_id.to_string(), token_id);
+
+        let mut token_series_ids: Vec<TokenSeriesId> = Vec::with_capacity(token_ids.len());
+        for (pos, token_id) in token_ids.iter().enumerate() {
+            if token_ids[..pos].contains(token_id) {
+                return Err(format!("Skins: duplicate token_id {}", token_id));
+            }
+            if self.tokens.owner_by_id.get(token_id).as_ref() != Some(owner_id) {
+                return Err(format!(
+                    "Skins: token_id {} is not owned by {}",
+                    token_id, owner_id
+                ));
+            }
+            let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
+            token_series_ids.push(token_id_iter.next().unwrap().to_string());
+        }
+
+        let series_metadata = self.internal_series_metadata(&token_series_ids);
+        let tokens: Vec<(&str, &TokenMetadata)> = token_series_ids
+            .iter()
+            .map(|token_series_id| (token_series_id.as_str(), &series_metadata[token_series_id]))
+            .collect();
+
+        // (index, missing ingredients, surplus token_ids, distance) of the closest fuse_requirement
+        let mut closest: Option<(usize, Vec<String>, Vec<TokenId>, u32)> = None;
+        for (idx, fuse_requirement) in fuse_requirements.iter().enumerate() {
+            let token_by_slot = fuse_requirement.assign(&tokens);
+            let slots = fuse_requirement.slots();
+            if token_by_slot.len() == tokens.len() && token_by_slot.iter().all(Option::is_some) {
+                let mut ingredient_by_token = vec![0; tokens.len()];
+                for (slot, token_idx) in token_by_slot.iter().enumerate() {
+                    ingredient_by_token[token_idx.unwrap()] = slots[slot];
+                }
+                return Ok((idx, ingredient_by_token));
+            }
+
+            let mut missing_by_ingredient: Vec<u32> = vec![0; fuse_requirement.ingredients.len()];
+            let mut is_used = vec![false; tokens.len()];
+            for (slot, token_idx) in token_by_slot.iter().enumerate() {
+                match token_idx {
+                    Some(token_idx) => is_used[*token_idx] = true,
+                    None => missing_by_ingredient[slots[slot]] += 1,
+                }
+            }
+            let missing: Vec<String> = missing_by_ingredient
+                .iter()
+                .enumerate()
+                .filter(|(_, quantity)| **quantity > 0)
+                .map(|(ingredient_idx, quantity)| {
+                    let mut ingredient = fuse_requirement.ingredients[ingredient_idx].clone();
+                    ingredient.quantity = *quantity;
+                    ingredient.to_string()
+                })
+                .collect();
+            let surplus: Vec<TokenId> = token_ids
+                .iter()
+                .zip(is_used.iter())
+                .filter(|(_, is_used)| !**is_used)
+                .map(|(token_id, _)| token_id.clone())
+                .collect();
+
+            let distance: u32 = missing_by_ingredient.iter().sum::<u32>() + surplus.len() as u32;
+            let is_closer = closest
+                .as_ref()
+                .map(|(_, _, _, closest_distance)| distance < *closest_distance)
+                .unwrap_or(true);
+            if is_closer {
+                closest = Some((idx, missing, surplus, distance));
+            }
+        }
+
+        match closest {
+            Some((idx, missing, surplus, _)) => Err(format!(
+                "Skins: fuse requirements not fulfilled, closest is fuse_requirements[{}] missing {:?} surplus token_ids {:?}",
+                idx, missing, surplus
+            )),
+            None => Err("Skins: Token series has no fuse requirements".to_string()),
+        }
+    }
+
+    fn internal_series_metadata(
+        &self,
+        token_series_ids: &[TokenSeriesId],
+    ) -> HashMap<TokenSeriesId, TokenMetadata> {
+        let mut series_metadata: HashMap<TokenSeriesId, TokenMetadata> = HashMap::new();
+        for token_series_id in token_series_ids {
+            if !series_metadata.contains_key(token_series_id) {
+                let token_series = self
+                    .token_series_by_id
+                    .get(token_series_id)
+                    .expect("Skins: Token series not exist");
+                series_metadata.insert(token_series_id.clone(), token_series.metadata);
             }
-            let token_id: TokenId =
-                self._nft_mint_series(target_token_series_id, receiver_id.to_string());
-            NearEvent::log_nft_mint(receiver_id.to_string(), vec![token_id.clone()], None);
-            self.last_token_by_owner.insert(&receiver_id, &token_id);
-            Some(token_id)
-        } else {
-            None
         }
+        series_metadata
     }
 
     pub fn get_last_token_by_owner(&self, account_id: ValidAccountId) -> TokenId {
-        self.last_token_by_owner.get(&account_id.to_string()).unwrap()
+        self.get_last_tokens_by_owner(account_id)
+            .pop()
+            .expect("Skins: no token minted by fuse")
+    }
+
+    pub fn get_last_tokens_by_owner(&self, account_id: ValidAccountId) -> Vec<TokenId> {
+        let account_id: AccountId = account_id.into();
+        self.last_tokens_by_owner.get(&account_id).unwrap_or_else(|| {
+            // single token kept by fuses from before migrate
+            LookupMap::<AccountId, TokenId>::new(StorageKey::LastTokenByOwner)
+                .get(&account_id)
+                .into_iter()
+                .collect()
+        })
     }
 
     #[payable]
@@ -719,11 +1264,61 @@ impl Contract {
         return price;
     }
 
+    /// None resets the series to TREASURY_FEE
+    #[payable]
+    pub fn nft_set_series_treasury_fee(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        treasury_fee: Option<u32>,
+    ) -> u32 {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        if let Some(treasury_fee) = treasury_fee {
+            assert!(treasury_fee <= 10_000, "Skins: treasury_fee must be at most 10000");
+        }
+
+        let mut token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        token_series.treasury_fee = treasury_fee;
+        self.token_series_by_id
+            .insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_treasury_fee",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "treasury_fee": token_series.treasury_fee(),
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        token_series.treasury_fee()
+    }
+
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        self.treasury_id = treasury_id.into();
+    }
+
     pub fn change_fuse_requirements(
         &mut self,
         token_series_id: TokenSeriesId,
-        fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
-        fuse_cost: Option<(AccountId, U128)>
+        fuse_requirements: Option<Vec<FuseRecipe>>,
+        fuse_cost: Option<FuseCost>
     ) {
         let mut token_series = self.token_series_by_id.get(&token_series_id).unwrap();
         assert_eq!(
@@ -731,33 +1326,121 @@ impl Contract {
             self.tokens.owner_id,
             "Skins: Owner only"
         );
-        token_series.fuse_requirements = fuse_requirements;
+        self.internal_assert_fuse_cost(&fuse_cost);
+        self.internal_assert_fuse_requirements(&fuse_requirements);
+        let previous_fuse_requirements = to_fuse_recipes(token_series.fuse_requirements.clone());
+        self.internal_remove_fuse_targets(&token_series_id, &previous_fuse_requirements);
+        self.internal_add_fuse_targets(&token_series_id, &fuse_requirements);
+        token_series.fuse_requirements = to_versioned_fuse_recipes(fuse_requirements);
         token_series.fuse_cost = fuse_cost;
         self.token_series_by_id.insert(&token_series_id, &token_series);
     }
-    #[payable]
-    pub fn nft_burn(&mut self, token_id: TokenId) {
-        assert_one_yocto();
-
-        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
-        assert_eq!(owner_id, env::predecessor_account_id(), "Token owner only");
 
-        self._nft_burn(owner_id, token_id);
+    fn internal_assert_fuse_cost(&self, fuse_cost: &Option<FuseCost>) {
+        for ft_token_id in fuse_cost.iter().flat_map(|fuse_cost| fuse_cost.keys()) {
+            assert!(
+                ft_token_id == NEAR_TOKEN_ID || self.approved_ft_token_ids.contains(ft_token_id),
+                "Skins: ft_token_id {} not approved",
+                ft_token_id
+            );
+        }
     }
 
-    fn _nft_burn(&mut self, owner_id: AccountId, token_id: TokenId) {
-        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
-            next_approval_id_by_id.remove(&token_id);
+    /// series named by the recipes outside of their ingredients must exist
+    fn internal_assert_fuse_requirements(&self, fuse_requirements: &Option<Vec<FuseRecipe>>) {
+        for fuse_requirement in fuse_requirements.iter().flatten() {
+            if let Some(FuseFailure::Consolation { token_series_id }) = &fuse_requirement.on_failure {
+                assert!(
+                    self.token_series_by_id.get(token_series_id).is_some(),
+                    "Skins: consolation token series {} not exist",
+                    token_series_id
+                );
+            }
         }
+    }
 
-        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
-            approvals_by_id.remove(&token_id);
+    pub fn add_approved_ft_token_ids(&mut self, ft_token_ids: Vec<ValidAccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        for ft_token_id in ft_token_ids {
+            self.approved_ft_token_ids.insert(ft_token_id.as_ref());
         }
+    }
 
-        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
-            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap();
-            token_ids.remove(&token_id);
-            tokens_per_owner.insert(&owner_id, &token_ids);
+    pub fn remove_approved_ft_token_ids(&mut self, ft_token_ids: Vec<ValidAccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        for ft_token_id in ft_token_ids {
+            self.approved_ft_token_ids.remove(ft_token_id.as_ref());
+        }
+    }
+
+    fn internal_add_fuse_targets(
+        &mut self,
+        target_token_series_id: &TokenSeriesId,
+        fuse_requirements: &Option<Vec<FuseRecipe>>,
+    ) {
+        for ingredient_id in fuse_ingredients(fuse_requirements) {
+            let mut targets = self
+                .fuse_targets_by_ingredient
+                .get(&ingredient_id)
+                .unwrap_or_else(|| {
+                    UnorderedSet::new(StorageKey::FuseTargetsByIngredientInner {
+                        token_series_id: ingredient_id.clone(),
+                    })
+                });
+            targets.insert(target_token_series_id);
+            self.fuse_targets_by_ingredient
+                .insert(&ingredient_id, &targets);
+        }
+    }
+
+    fn internal_remove_fuse_targets(
+        &mut self,
+        target_token_series_id: &TokenSeriesId,
+        fuse_requirements: &Option<Vec<FuseRecipe>>,
+    ) {
+        for ingredient_id in fuse_ingredients(fuse_requirements) {
+            if let Some(mut targets) = self.fuse_targets_by_ingredient.get(&ingredient_id) {
+                targets.remove(target_token_series_id);
+                if targets.is_empty() {
+                    self.fuse_targets_by_ingredient.remove(&ingredient_id);
+                } else {
+                    self.fuse_targets_by_ingredient
+                        .insert(&ingredient_id, &targets);
+                }
+            }
+        }
+    }
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+
+        let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
+        assert_eq!(owner_id, env::predecessor_account_id(), "Token owner only");
+
+        self._nft_burn(owner_id, token_id);
+    }
+
+    fn _nft_burn(&mut self, owner_id: AccountId, token_id: TokenId) {
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(&token_id);
+        }
+
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(&token_id);
+        }
+
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap();
+            token_ids.remove(&token_id);
+            tokens_per_owner.insert(&owner_id, &token_ids);
         }
 
         if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
@@ -778,12 +1461,130 @@ impl Contract {
             .expect("Series does not exist");
         TokenSeriesJson {
             token_series_id,
+            treasury_fee: token_series.treasury_fee(),
+            sale_status: token_series.sale_status(),
             metadata: token_series.metadata,
             creator_id: token_series.creator_id,
             royalty: token_series.royalty,
-            fuse_requirements: token_series.fuse_requirements,
-            fuse_cost: token_series.fuse_cost
+            fuse_requirements: to_fuse_recipes(token_series.fuse_requirements),
+            fuse_cost: token_series.fuse_cost,
+            sale_starts_at: token_series.sale_starts_at.map(U64),
+            sale_ends_at: token_series.sale_ends_at.map(U64),
+            sale_config: token_series.sale_config,
+            price_curve: token_series.price_curve,
+            ft_price: token_series.ft_price,
+        }
+    }
+
+    pub fn nft_get_fuse_targets(&self, token_series_id: TokenSeriesId) -> Vec<TokenSeriesId> {
+        self.fuse_targets_by_ingredient
+            .get(&token_series_id)
+            .map(|targets| targets.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// target series that account_id can fuse now with the tokens it owns
+    pub fn nft_fusable_series_for_owner(&self, account_id: ValidAccountId) -> Vec<TokenSeriesId> {
+        let tokens_per_owner = self.tokens.tokens_per_owner.as_ref().expect(
+            "Could not find tokens_per_owner when calling a method on the enumeration standard.",
+        );
+        let token_set = if let Some(token_set) = tokens_per_owner.get(account_id.as_ref()) {
+            token_set
+        } else {
+            return vec![];
+        };
+
+        let token_series_ids: Vec<TokenSeriesId> = token_set
+            .iter()
+            .map(|token_id| {
+                let mut token_id_iter = token_id.split(TOKEN_DELIMETER);
+                token_id_iter.next().unwrap().to_string()
+            })
+            .collect();
+        let series_metadata = self.internal_series_metadata(&token_series_ids);
+        let tokens: Vec<(&str, &TokenMetadata)> = token_series_ids
+            .iter()
+            .map(|token_series_id| (token_series_id.as_str(), &series_metadata[token_series_id]))
+            .collect();
+
+        let mut ingredient_ids: Vec<TokenSeriesId> = vec![];
+        for token_series_id in token_series_ids.iter() {
+            if !ingredient_ids.contains(token_series_id) {
+                ingredient_ids.push(token_series_id.clone());
+            }
+        }
+        ingredient_ids.push(FUSE_INGREDIENT_ANY.to_string());
+
+        let mut candidates: Vec<TokenSeriesId> = vec![];
+        for ingredient_id in ingredient_ids.iter() {
+            if let Some(targets) = self.fuse_targets_by_ingredient.get(ingredient_id) {
+                for target_id in targets.iter() {
+                    if !candidates.contains(&target_id) {
+                        candidates.push(target_id);
+                    }
+                }
+            }
         }
+
+        candidates
+            .into_iter()
+            .filter(|target_id| {
+                let token_series = match self.token_series_by_id.get(target_id) {
+                    Some(token_series) => token_series,
+                    None => return false,
+                };
+                to_fuse_recipes(token_series.fuse_requirements)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|fuse_requirement| {
+                        fuse_requirement
+                            .assign(&tokens)
+                            .iter()
+                            .all(Option::is_some)
+                            && self
+                                .internal_check_supply(&fuse_requirement.outputs_for(target_id))
+                                .is_ok()
+                    })
+            })
+            .collect()
+    }
+
+    pub fn get_approved_ft_token_ids(&self) -> Vec<AccountId> {
+        self.approved_ft_token_ids.to_vec()
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+
+    /// split of balance between treasury and creator when token_series_id is bought with nft_buy
+    pub fn nft_primary_sale_payout(&self, token_series_id: TokenSeriesId, balance: U128) -> Payout {
+        let token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        self.internal_primary_sale_payout(&token_series, balance.0)
+    }
+
+    fn internal_primary_sale_payout(&self, token_series: &TokenSeries, balance: Balance) -> Payout {
+        let treasury_amount = royalty_to_payout(token_series.treasury_fee(), balance).0;
+
+        let mut payout: Payout = Payout {
+            payout: HashMap::new(),
+        };
+        payout
+            .payout
+            .insert(self.treasury_id.clone(), U128(treasury_amount));
+        let creator_amount = payout
+            .payout
+            .get(&token_series.creator_id)
+            .map_or(0, |amount| amount.0)
+            + balance
+            - treasury_amount;
+        payout
+            .payout
+            .insert(token_series.creator_id.clone(), U128(creator_amount));
+        payout
     }
 
     pub fn nft_get_series_format(self) -> (char, &'static str, &'static str) {
@@ -817,11 +1618,18 @@ impl Contract {
             .take(limit)
             .map(|(token_series_id, token_series)| TokenSeriesJson {
                 token_series_id,
+                treasury_fee: token_series.treasury_fee(),
+                sale_status: token_series.sale_status(),
                 metadata: token_series.metadata,
                 creator_id: token_series.creator_id,
                 royalty: token_series.royalty,
-                fuse_requirements: token_series.fuse_requirements,
-                fuse_cost: token_series.fuse_cost
+                fuse_requirements: to_fuse_recipes(token_series.fuse_requirements),
+                fuse_cost: token_series.fuse_cost,
+                sale_starts_at: token_series.sale_starts_at.map(U64),
+                sale_ends_at: token_series.sale_ends_at.map(U64),
+                sale_config: token_series.sale_config,
+                price_curve: token_series.price_curve,
+                ft_price: token_series.ft_price,
             })
             .collect()
     }
@@ -1212,6 +2020,47 @@ impl Contract {
     }
 }
 
+/// unique series ids used by any of fuse_requirements
+fn fuse_ingredients(fuse_requirements: &Option<Vec<FuseRecipe>>) -> Vec<TokenSeriesId> {
+    let mut ingredients: Vec<TokenSeriesId> = vec![];
+    for fuse_requirement in fuse_requirements.iter().flatten() {
+        for token_series_id in fuse_requirement.ingredient_series_ids() {
+            if !ingredients.contains(&token_series_id) {
+                ingredients.push(token_series_id);
+            }
+        }
+    }
+    ingredients
+}
+
+fn to_versioned_fuse_recipes(
+    fuse_requirements: Option<Vec<FuseRecipe>>,
+) -> Option<Vec<VersionedFuseRecipe>> {
+    fuse_requirements.map(|fuse_requirements| {
+        fuse_requirements
+            .into_iter()
+            .map(|fuse_requirement| {
+                fuse_requirement.assert_valid();
+                VersionedFuseRecipe::from(fuse_requirement)
+            })
+            .collect()
+    })
+}
+
+fn to_fuse_recipes(fuse_requirements: Option<Vec<VersionedFuseRecipe>>) -> Option<Vec<FuseRecipe>> {
+    fuse_requirements.map(|fuse_requirements| {
+        fuse_requirements
+            .into_iter()
+            .map(FuseRecipe::from)
+            .collect()
+    })
+}
+
+/// roll in 0..FUSE_RATE_DENOMINATOR from random_seed
+fn random_roll(random_seed: &[u8]) -> u16 {
+    loot::random_below(random_seed, FUSE_RATE_DENOMINATOR as u64) as u16
+}
+
 fn royalty_to_payout(a: u32, b: Balance) -> U128 {
     U128(a as u128 * b / 10_000u128)
 }
@@ -1271,12 +2120,27 @@ fn refund_deposit(storage_used: u64, extra_spend: Balance) {
     }
 }
 
+/// Sends the storage freed since initial_storage_usage back to account_id
+fn refund_freed_storage(account_id: &AccountId, initial_storage_usage: u64) {
+    let freed_cost = env::storage_byte_cost()
+        * Balance::from(initial_storage_usage.saturating_sub(env::storage_usage()));
+    if freed_cost > 1 {
+        Promise::new(account_id.clone()).transfer(freed_cost);
+    }
+}
+
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::testing_env;
     use near_sdk::MockedBlockchain;
+    use near_sdk::PromiseResult;
+    use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+    use crate::token_receiver::FT_ON_TRANSFER_MSG_VERSION;
+    use near_sdk::json_types::Base64VecU8;
 
     const STORAGE_FOR_CREATE_SERIES: Balance = 8540000000000000000000;
     const STORAGE_FOR_MINT: Balance = 11280000000000000000000;
@@ -1354,7 +2218,84 @@ mod tests {
             price,
             Some(royalty.clone()),
             None,
+            None,
+        );
+    }
+
+    fn create_fuse_series(
+        contract: &mut Contract,
+        fuse_requirements: Vec<FuseRecipe>,
+        fuse_cost: Option<FuseCost>,
+    ) {
+        contract.nft_create_series(
+            None,
+            TokenMetadata {
+                title: Some("Fused land".to_string()),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                issued_at: None,
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            },
+            None,
+            None,
+            Some(fuse_requirements),
+            fuse_cost,
+        );
+    }
+
+    fn recipe(token_series_ids: &[&str]) -> FuseRecipe {
+        FuseRecipe::from_token_series_ids(
+            token_series_ids.iter().map(|id| id.to_string()).collect(),
+        )
+    }
+
+    fn fuse_cost(prices: &[(&str, u128)]) -> Option<FuseCost> {
+        Some(
+            prices
+                .iter()
+                .map(|(ft_token_id, price)| (ft_token_id.to_string(), U128::from(*price)))
+                .collect(),
+        )
+    }
+
+    /// series 1 and 2 are ingredients for series 3, accounts(4) is an approved FT, returns tokens minted to accounts(2)
+    fn setup_fuse(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        fuse_cost: Option<FuseCost>,
+    ) -> Vec<TokenId> {
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        contract.add_approved_ft_token_ids(vec![accounts(4)]);
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(contract, &royalty, None, None);
+        create_series(contract, &royalty, None, None);
+        create_fuse_series(
+            contract,
+            vec![recipe(&["1", "2"])],
+            fuse_cost,
         );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        vec![
+            contract.nft_mint("1".to_string(), accounts(2)),
+            contract.nft_mint("2".to_string(), accounts(2)),
+        ]
     }
 
     #[test]
@@ -1426,111 +2367,574 @@ mod tests {
         )
     }
 
-    #[test]
-    fn test_mint() {
-        let (mut context, mut contract) = setup_contract();
+    /// accounts(0) creates a series for each (price, copies), then calls on with attached_deposit
+    fn setup_series(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        series: &[(Option<U128>, Option<u64>)],
+        attached_deposit: Balance,
+    ) {
         testing_env!(context
             .predecessor_account_id(accounts(0))
             .attached_deposit(STORAGE_FOR_CREATE_SERIES)
             .build());
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
-
-        create_series(&mut contract, &royalty, None, None);
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        for (price, copies) in series {
+            create_series(contract, &royalty, *price, *copies);
+        }
 
         testing_env!(context
             .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .attached_deposit(attached_deposit)
             .build());
+    }
 
-        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+    /// series 1 at 1 NEAR with 5 copies
+    fn setup_priced_series(context: &mut VMContextBuilder, contract: &mut Contract) {
+        setup_series(context, contract, &[(Some(U128::from(1 * 10u128.pow(24))), Some(5))], 1);
+    }
 
-        let token_from_nft_token = contract.nft_token(token_id);
+    fn setup_buy_many(context: &mut VMContextBuilder, contract: &mut Contract, quantity: u32) {
+        setup_priced_series(context, contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(quantity as u128 * (1 * 10u128.pow(24) + STORAGE_FOR_MINT))
+            .build());
+    }
+
+    #[test]
+    fn test_buy_many() {
+        let (mut context, mut contract) = setup_contract();
+        setup_buy_many(&mut context, &mut contract, 3);
+
+        let token_ids = contract.nft_buy_many("1".to_string(), accounts(2), 3);
         assert_eq!(
-            token_from_nft_token.unwrap().owner_id,
-            accounts(2).to_string()
-        )
+            token_ids,
+            vec!["1:1".to_string(), "1:2".to_string(), "1:3".to_string()]
+        );
+        for token_id in token_ids {
+            assert_eq!(
+                contract.nft_token(token_id).unwrap().owner_id,
+                accounts(2).to_string()
+            );
+        }
     }
 
     #[test]
-    #[should_panic(expected = "Skins: Token series is not mintable")]
-    fn test_invalid_mint_non_mintable() {
+    #[should_panic(expected = "Skins: only 5 tokens left in series")]
+    fn test_invalid_buy_many_exceeds_copies() {
         let (mut context, mut contract) = setup_contract();
-        testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
-            .build());
+        setup_buy_many(&mut context, &mut contract, 6);
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
+        contract.nft_buy_many("1".to_string(), accounts(2), 6);
+    }
 
-        create_series(&mut contract, &royalty, None, None);
+    #[test]
+    #[should_panic(expected = "Skins: quantity exceeds max_buy_quantity 2")]
+    fn test_invalid_buy_many_exceeds_max_buy_quantity() {
+        let (mut context, mut contract) = setup_contract();
+        contract.set_max_buy_quantity(2);
+        setup_buy_many(&mut context, &mut contract, 3);
 
-        testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(1)
-            .build());
-        contract.nft_set_series_non_mintable("1".to_string());
+        contract.nft_buy_many("1".to_string(), accounts(2), 3);
+    }
+
+    /// series 1 at 1 NEAR with the sale_config, accounts(2) is allowlisted
+    fn setup_sale(context: &mut VMContextBuilder, contract: &mut Contract, sale_config: SaleConfig) {
+        setup_priced_series(context, contract);
+        contract.nft_set_series_sale_config("1".to_string(), Some(sale_config));
 
         testing_env!(context
             .predecessor_account_id(accounts(0))
             .attached_deposit(STORAGE_FOR_MINT)
             .build());
-
-        contract.nft_mint("1".to_string(), accounts(2));
+        contract.nft_add_series_allowlist("1".to_string(), vec![accounts(2)]);
     }
 
     #[test]
-    #[should_panic(expected = "Skins: Token series is not mintable")]
-    fn test_invalid_mint_above_copies() {
+    fn test_buy_allowlist_phase() {
         let (mut context, mut contract) = setup_contract();
+        setup_sale(
+            &mut context,
+            &mut contract,
+            SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: Some(2),
+                allowlist_price: Some(U128::from(5 * 10u128.pow(23))),
+                allowlist_ft_price: None,
+            },
+        );
+        assert!(contract.nft_is_allowlisted("1".to_string(), accounts(2)));
+
         testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(5 * 10u128.pow(23) + STORAGE_FOR_MINT)
             .build());
+        contract.nft_buy("1".to_string(), accounts(2));
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
+        assert_eq!(contract.nft_purchases_by_account("1".to_string(), accounts(2)), 1);
+        assert_eq!(contract.nft_purchases_by_account("1".to_string(), accounts(3)), 0);
+    }
 
-        create_series(&mut contract, &royalty, None, Some(1));
+    #[test]
+    #[should_panic(expected = "Skins: not for sale")]
+    fn test_invalid_buy_allowlist_price_not_for_sale() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale(
+            &mut context,
+            &mut contract,
+            SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: None,
+                allowlist_price: Some(U128::from(5 * 10u128.pow(23))),
+                allowlist_ft_price: None,
+            },
+        );
 
         testing_env!(context
             .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .attached_deposit(1)
             .build());
+        contract.nft_set_series_price("1".to_string(), None);
 
-        contract.nft_mint("1".to_string(), accounts(2));
-        contract.nft_mint("1".to_string(), accounts(2));
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(5 * 10u128.pow(23) + STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy("1".to_string(), accounts(2));
     }
 
     #[test]
-    fn test_decrease_copies() {
+    #[should_panic(expected = "is not allowlisted")]
+    fn test_invalid_buy_not_allowlisted() {
         let (mut context, mut contract) = setup_contract();
+        setup_sale(
+            &mut context,
+            &mut contract,
+            SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: None,
+                allowlist_price: None,
+                allowlist_ft_price: None,
+            },
+        );
+
         testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
             .build());
+        contract.nft_buy("1".to_string(), accounts(3));
+    }
 
-        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
-        royalty.insert(accounts(1).to_string(), 1000);
-
-        create_series(&mut contract, &royalty, None, Some(5));
+    #[test]
+    #[should_panic(expected = "Skins: per wallet cap of 2 reached")]
+    fn test_invalid_buy_per_wallet_cap() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale(
+            &mut context,
+            &mut contract,
+            SaleConfig {
+                phase: SalePhase::Public,
+                per_wallet_cap: Some(2),
+                allowlist_price: None,
+                allowlist_ft_price: None,
+            },
+        );
 
         testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(STORAGE_FOR_MINT)
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(3 * (1 * 10u128.pow(24) + STORAGE_FOR_MINT))
             .build());
+        contract.nft_buy("1".to_string(), accounts(3));
+        contract.nft_buy_many("1".to_string(), accounts(3), 2);
+    }
 
-        contract.nft_mint("1".to_string(), accounts(2));
-        contract.nft_mint("1".to_string(), accounts(2));
+    /// series 1 on sale from 1_000 until 2_000
+    fn setup_sale_window(context: &mut VMContextBuilder, contract: &mut Contract) {
+        setup_priced_series(context, contract);
+        contract.nft_set_series_sale_window(
+            "1".to_string(),
+            Some(U64::from(1_000)),
+            Some(U64::from(2_000)),
+        );
+    }
 
+    fn buy_at(context: &mut VMContextBuilder, contract: &mut Contract, block_timestamp: u64) -> TokenId {
         testing_env!(context
-            .predecessor_account_id(accounts(0))
-            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(block_timestamp)
+            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
             .build());
-
-        contract.nft_decrease_series_copies("1".to_string(), U64::from(3));
+        contract.nft_buy("1".to_string(), accounts(2))
+    }
+
+    #[test]
+    fn test_buy_sale_window() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale_window(&mut context, &mut contract);
+
+        assert_eq!(
+            contract.nft_get_series_single("1".to_string()).sale_status,
+            SaleStatus::Upcoming
+        );
+
+        buy_at(&mut context, &mut contract, 1_000);
+        assert_eq!(
+            contract.nft_get_series_single("1".to_string()).sale_status,
+            SaleStatus::Active
+        );
+
+        testing_env!(context.block_timestamp(2_000).build());
+        assert_eq!(
+            contract.nft_get_series_single("1".to_string()).sale_status,
+            SaleStatus::Ended
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: sale not started")]
+    fn test_invalid_buy_before_sale_starts() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale_window(&mut context, &mut contract);
+
+        buy_at(&mut context, &mut contract, 999);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: sale ended")]
+    fn test_invalid_buy_after_sale_ends() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale_window(&mut context, &mut contract);
+
+        buy_at(&mut context, &mut contract, 2_000);
+    }
+
+    fn setup_price_curve(context: &mut VMContextBuilder, contract: &mut Contract, price_curve: PriceCurve) {
+        setup_priced_series(context, contract);
+        contract.nft_set_series_price_curve("1".to_string(), Some(price_curve));
+    }
+
+    fn dutch_auction() -> PriceCurve {
+        PriceCurve::DutchAuction {
+            start_price: U128::from(10 * 10u128.pow(24)),
+            floor_price: U128::from(2 * 10u128.pow(24)),
+            starts_at: U64::from(1_000),
+            ends_at: U64::from(2_000),
+        }
+    }
+
+    fn quote_at(context: &mut VMContextBuilder, contract: &Contract, block_timestamp: u64) -> u128 {
+        testing_env!(context.block_timestamp(block_timestamp).build());
+        contract.nft_get_series_quote("1".to_string(), None).unwrap().0
+    }
+
+    #[test]
+    fn test_dutch_auction_quote() {
+        let (mut context, mut contract) = setup_contract();
+        setup_price_curve(&mut context, &mut contract, dutch_auction());
+
+        assert_eq!(quote_at(&mut context, &contract, 0), 10 * 10u128.pow(24));
+        assert_eq!(quote_at(&mut context, &contract, 1_000), 10 * 10u128.pow(24));
+        assert_eq!(quote_at(&mut context, &contract, 1_500), 6 * 10u128.pow(24));
+        assert_eq!(quote_at(&mut context, &contract, 1_750), 4 * 10u128.pow(24));
+        assert_eq!(quote_at(&mut context, &contract, 2_000), 2 * 10u128.pow(24));
+        assert_eq!(quote_at(&mut context, &contract, 5_000), 2 * 10u128.pow(24));
+    }
+
+    #[test]
+    fn test_buy_dutch_auction() {
+        let (mut context, mut contract) = setup_contract();
+        setup_price_curve(&mut context, &mut contract, dutch_auction());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_500)
+            .attached_deposit(6 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        let token_id = contract.nft_buy("1".to_string(), accounts(2));
+        assert_eq!(token_id, "1:1".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: attached deposit is less than price")]
+    fn test_invalid_buy_dutch_auction_underpaid() {
+        let (mut context, mut contract) = setup_contract();
+        setup_price_curve(&mut context, &mut contract, dutch_auction());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_400)
+            .attached_deposit(6 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy("1".to_string(), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: not for sale")]
+    fn test_invalid_buy_price_curve_without_price() {
+        let (mut context, mut contract) = setup_contract();
+        setup_price_curve(&mut context, &mut contract, dutch_auction());
+        contract.nft_set_series_price("1".to_string(), None);
+
+        assert_eq!(contract.nft_get_series_quote("1".to_string(), None), None);
+        assert_eq!(
+            contract.nft_get_series_single("1".to_string()).sale_status,
+            SaleStatus::NotForSale
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_500)
+            .attached_deposit(10 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy("1".to_string(), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: series has no price, set a price before a price_curve")]
+    fn test_invalid_price_curve_unpriced_series() {
+        let (mut context, mut contract) = setup_contract();
+        setup_series(&mut context, &mut contract, &[(None, Some(5))], 1);
+
+        contract.nft_set_series_price_curve("1".to_string(), Some(dutch_auction()));
+    }
+
+    #[test]
+    fn test_buy_allowlist_price_above_dutch_auction() {
+        let (mut context, mut contract) = setup_contract();
+        setup_sale(
+            &mut context,
+            &mut contract,
+            SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: None,
+                allowlist_price: Some(U128::from(8 * 10u128.pow(24))),
+                allowlist_ft_price: None,
+            },
+        );
+        testing_env!(context.attached_deposit(1).build());
+        contract.nft_set_series_price_curve("1".to_string(), Some(dutch_auction()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_000)
+            .attached_deposit(8 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy("1".to_string(), accounts(2));
+
+        testing_env!(context
+            .block_timestamp(1_500)
+            .attached_deposit(6 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy("1".to_string(), accounts(2));
+        assert_eq!(contract.nft_purchases_by_account("1".to_string(), accounts(2)), 2);
+    }
+
+    #[test]
+    fn test_bonding_curve_quote() {
+        let (mut context, mut contract) = setup_contract();
+        setup_price_curve(
+            &mut context,
+            &mut contract,
+            PriceCurve::BondingCurve {
+                base_price: U128::from(1 * 10u128.pow(24)),
+                increment: U128::from(1 * 10u128.pow(23)),
+            },
+        );
+
+        assert_eq!(quote_at(&mut context, &contract, 0), 1 * 10u128.pow(24));
+        assert_eq!(
+            contract.nft_get_series_quote("1".to_string(), Some(3)).unwrap().0,
+            33 * 10u128.pow(23)
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(33 * 10u128.pow(23) + 3 * STORAGE_FOR_MINT)
+            .build());
+        contract.nft_buy_many("1".to_string(), accounts(2), 3);
+
+        assert_eq!(quote_at(&mut context, &contract, 0), 13 * 10u128.pow(23));
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover storage")]
+    fn test_invalid_buy_without_storage_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(
+            &mut contract,
+            &royalty,
+            Some(U128::from(1 * 10u128.pow(24))),
+            None,
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1 * 10u128.pow(24))
+            .build());
+
+        contract.nft_buy("1".to_string(), accounts(2));
+    }
+
+    #[test]
+    fn test_primary_sale_payout() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(&mut contract, &royalty, Some(U128::from(10_000)), None);
+
+        let payout = contract.nft_primary_sale_payout("1".to_string(), U128::from(10_000));
+        assert_eq!(payout.payout.len(), 1);
+        assert_eq!(payout.payout[&accounts(0).to_string()].0, 10_000);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.set_treasury(accounts(3));
+
+        let payout = contract.nft_primary_sale_payout("1".to_string(), U128::from(10_000));
+        assert_eq!(payout.payout[&accounts(3).to_string()].0, 500);
+        assert_eq!(payout.payout[&accounts(0).to_string()].0, 9_500);
+
+        assert_eq!(
+            contract.nft_set_series_treasury_fee("1".to_string(), Some(1_000)),
+            1_000
+        );
+        let payout = contract.nft_primary_sale_payout("1".to_string(), U128::from(10_000));
+        assert_eq!(payout.payout[&accounts(3).to_string()].0, 1_000);
+        assert_eq!(payout.payout[&accounts(0).to_string()].0, 9_000);
+        assert_eq!(contract.nft_get_series_single("1".to_string()).treasury_fee, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Owner only")]
+    fn test_invalid_set_treasury_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build());
+        contract.set_treasury(accounts(1));
+    }
+
+    #[test]
+    fn test_mint() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        let token_id = contract.nft_mint("1".to_string(), accounts(2));
+
+        let token_from_nft_token = contract.nft_token(token_id);
+        assert_eq!(
+            token_from_nft_token.unwrap().owner_id,
+            accounts(2).to_string()
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Token series is not mintable")]
+    fn test_invalid_mint_non_mintable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.nft_set_series_non_mintable("1".to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        contract.nft_mint("1".to_string(), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Token series is not mintable")]
+    fn test_invalid_mint_above_copies() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(1));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        contract.nft_mint("1".to_string(), accounts(2));
+        contract.nft_mint("1".to_string(), accounts(2));
+    }
+
+    #[test]
+    fn test_decrease_copies() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        royalty.insert(accounts(1).to_string(), 1000);
+
+        create_series(&mut contract, &royalty, None, Some(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        contract.nft_mint("1".to_string(), accounts(2));
+        contract.nft_mint("1".to_string(), accounts(2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_decrease_series_copies("1".to_string(), U64::from(3));
     }
 
     #[test]
@@ -1740,4 +3144,1870 @@ mod tests {
         let token = contract.nft_token(token_id).unwrap();
         assert_eq!(token.owner_id, accounts(3).to_string())
     }
+
+    #[test]
+    fn test_nft_fuse_native_cost() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(NEAR_TOKEN_ID, 1 * 10u128.pow(24))]),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1 * 10u128.pow(24))
+            .build());
+
+        let token_id = contract
+            .nft_fuse(token_ids.clone(), "3".to_string(), None)
+            .token_ids
+            .pop()
+            .unwrap();
+
+        assert_eq!(token_id, "3:1");
+        assert_eq!(
+            contract.nft_token(token_id).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+        assert!(contract.nft_token(token_ids[0].clone()).is_none());
+        assert!(contract.nft_token(token_ids[1].clone()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: attached deposit is less than fuse cost")]
+    fn test_invalid_nft_fuse_native_cost_not_paid() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(NEAR_TOKEN_ID, 1 * 10u128.pow(24))]),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: fuse cost must be paid through ft_transfer_call")]
+    fn test_invalid_nft_fuse_ft_cost() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+    }
+
+    #[test]
+    fn test_nft_fuse_by_approved_operator() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        for token_id in token_ids.iter() {
+            contract.nft_approve(token_id.clone(), accounts(3), None);
+        }
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build());
+
+        let token_id = contract
+            .nft_fuse(token_ids, "3".to_string(), Some(accounts(2)))
+            .token_ids
+            .pop()
+            .unwrap();
+
+        assert_eq!(
+            contract.nft_token(token_id).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: caller is not approved for 1:1")]
+    fn test_invalid_nft_fuse_not_approved() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(token_ids, "3".to_string(), Some(accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: duplicate token_id 1:1")]
+    fn test_invalid_nft_fuse_duplicate_token_ids() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(
+            vec![token_ids[0].clone(), token_ids[0].clone(), token_ids[1].clone()],
+            "3".to_string(),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "closest is fuse_requirements[0] missing [] surplus token_ids")]
+    fn test_invalid_nft_fuse_surplus_token() {
+        let (mut context, mut contract) = setup_contract();
+        let mut token_ids = setup_fuse(&mut context, &mut contract, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        token_ids.push(contract.nft_mint("1".to_string(), accounts(2)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+    }
+
+    #[test]
+    fn test_match_fuse_requirement_closest() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+
+        let fuse_requirements = vec![recipe(&["2", "2"]), recipe(&["1", "1", "2"])];
+        let owner_id = accounts(2).to_string();
+
+        let err = contract
+            .internal_match_fuse_requirement(&token_ids, &fuse_requirements, &owner_id)
+            .unwrap_err();
+        assert!(err.contains("closest is fuse_requirements[1]"));
+        assert!(err.contains(r#"missing ["1 of series [\"1\"]"] surplus token_ids []"#));
+
+        assert_eq!(
+            contract.internal_match_fuse_requirement(&token_ids, &[recipe(&["2", "1"])], &owner_id),
+            Ok((0, vec![1, 0]))
+        );
+    }
+
+    #[test]
+    fn test_nft_fuse_preview() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(NEAR_TOKEN_ID, 100), (accounts(4).to_string().as_str(), 5)]),
+        );
+
+        testing_env!(context.is_view(true).build());
+
+        let preview = contract.nft_fuse_preview(token_ids.clone(), "3".to_string(), None);
+        assert_eq!(preview.fuse_requirement_index, Some(0));
+        assert_eq!(preview.burned_token_ids, token_ids);
+        assert_eq!(preview.fuse_cost.unwrap()[NEAR_TOKEN_ID], U128::from(100));
+        assert!(preview.has_supply);
+        assert!(preview.error.is_none());
+
+        let preview = contract.nft_fuse_preview(
+            vec![token_ids[0].clone()],
+            "3".to_string(),
+            Some(accounts(2)),
+        );
+        assert!(preview.fuse_requirement_index.is_none());
+        assert!(preview.burned_token_ids.is_empty());
+        assert!(preview.error.unwrap().contains(r#"missing ["1 of series [\"2\"]"]"#));
+
+        // nothing is burned by the preview
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_fuse_targets_index() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+
+        assert_eq!(contract.nft_get_fuse_targets("1".to_string()), vec!["3".to_string()]);
+        assert_eq!(
+            contract.nft_fusable_series_for_owner(accounts(2)),
+            vec!["3".to_string()]
+        );
+        assert!(contract.nft_fusable_series_for_owner(accounts(3)).is_empty());
+
+        contract.change_fuse_requirements(
+            "3".to_string(),
+            Some(vec![recipe(&["2", "2"])]),
+            None,
+        );
+        assert!(contract.nft_get_fuse_targets("1".to_string()).is_empty());
+        assert_eq!(contract.nft_get_fuse_targets("2".to_string()), vec!["3".to_string()]);
+        assert!(contract.nft_fusable_series_for_owner(accounts(2)).is_empty());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        contract.nft_mint("2".to_string(), accounts(2));
+        assert_eq!(
+            contract.nft_fusable_series_for_owner(accounts(2)),
+            vec!["3".to_string()]
+        );
+    }
+
+    fn setup_chance_fuse(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        on_failure: Option<FuseFailure>,
+    ) -> Vec<TokenId> {
+        let token_ids = setup_fuse(context, contract, None);
+        // series 4 is the consolation series
+        create_series(contract, &HashMap::new(), None, None);
+
+        let mut fuse_requirement = recipe(&["1", "2"]);
+        fuse_requirement.success_rate = Some(5000);
+        fuse_requirement.on_failure = on_failure;
+        contract.change_fuse_requirements("3".to_string(), Some(vec![fuse_requirement]), None);
+        token_ids
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: consolation token series 9 not exist")]
+    fn test_invalid_fuse_consolation_series() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(
+            &mut context,
+            &mut contract,
+            Some(FuseFailure::Consolation {
+                token_series_id: "9".to_string(),
+            }),
+        );
+    }
+
+    /// deposits token_ids of accounts(2) on the workbench of series 3 and commits in block 1,
+    /// then reveals in block 2 with the first random_seed whose roll is a success or not
+    fn fuse_chance(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_ids: &[TokenId],
+        is_success: bool,
+    ) -> FuseOutcome {
+        let token_ids: Vec<&str> = token_ids.iter().map(|token_id| token_id.as_str()).collect();
+        workbench_deposit(context, contract, &token_ids);
+        testing_env!(context.block_index(1).build());
+        contract.nft_commit_fuse("3".to_string(), loot_salt_hash());
+
+        let random_seed = (0..1_000)
+            .map(loot_random_seed)
+            .find(|random_seed| {
+                let roll = random_roll(&reveal_seed(random_seed, LOOT_SALT, 1));
+                (roll < 5_000) == is_success
+            })
+            .unwrap();
+        testing_env!(context
+            .attached_deposit(STORAGE_FOR_MINT)
+            .block_index(2)
+            .random_seed(random_seed)
+            .build());
+        contract.nft_reveal_fuse("3".to_string(), LOOT_SALT.to_string())
+    }
+
+    #[test]
+    fn test_nft_fuse_chance_success() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_chance_fuse(&mut context, &mut contract, None);
+
+        let fuse_outcome = fuse_chance(&mut context, &mut contract, &token_ids, true);
+        assert!(fuse_outcome.is_success);
+        assert!(fuse_outcome.roll.unwrap() < 5_000);
+        assert_eq!(fuse_outcome.token_ids, vec!["3:1".to_string()]);
+        assert!(contract.nft_get_fuse_request(accounts(2), "3".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_nft_fuse_chance_failure_consolation() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_chance_fuse(
+            &mut context,
+            &mut contract,
+            Some(FuseFailure::Consolation {
+                token_series_id: "4".to_string(),
+            }),
+        );
+
+        let fuse_outcome = fuse_chance(&mut context, &mut contract, &token_ids, false);
+        assert!(!fuse_outcome.is_success);
+        assert!(fuse_outcome.roll.unwrap() >= 5_000);
+        assert_eq!(fuse_outcome.token_ids, vec!["4:1".to_string()]);
+        assert_eq!(fuse_outcome.burned_token_ids, token_ids);
+    }
+
+    #[test]
+    fn test_nft_fuse_chance_failure_burn_ingredients() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_chance_fuse(
+            &mut context,
+            &mut contract,
+            Some(FuseFailure::BurnIngredients {
+                ingredient_indexes: vec![1],
+            }),
+        );
+
+        let fuse_outcome = fuse_chance(&mut context, &mut contract, &token_ids, false);
+        assert!(!fuse_outcome.is_success);
+        assert!(fuse_outcome.token_ids.is_empty());
+        assert_eq!(fuse_outcome.burned_token_ids, vec![token_ids[1].clone()]);
+        assert_eq!(
+            contract.nft_token(token_ids[0].clone()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+        assert!(contract.nft_token(token_ids[1].clone()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Token series 4 supply maxed")]
+    fn test_invalid_nft_fuse_chance_consolation_maxed() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_chance_fuse(
+            &mut context,
+            &mut contract,
+            Some(FuseFailure::Consolation {
+                token_series_id: "4".to_string(),
+            }),
+        );
+        let mut token_series = contract.token_series_by_id.get(&"4".to_string()).unwrap();
+        token_series.metadata.copies = Some(0);
+        contract.token_series_by_id.insert(&"4".to_string(), &token_series);
+
+        // a success would not mint series 4, the reveal fails before the roll all the same
+        fuse_chance(&mut context, &mut contract, &token_ids, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: fuse_requirement with a success_rate must go through nft_commit_fuse")]
+    fn test_invalid_nft_fuse_chance_direct() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_chance_fuse(&mut context, &mut contract, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: fuse request can be revealed from the next block")]
+    fn test_invalid_nft_reveal_fuse_same_block() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(&mut context, &mut contract, None);
+        workbench_deposit(&mut context, &mut contract, &["1:1", "2:1"]);
+
+        contract.nft_commit_fuse("3".to_string(), loot_salt_hash());
+        contract.nft_reveal_fuse("3".to_string(), LOOT_SALT.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: fuse request pending for the workbench")]
+    fn test_invalid_fuse_workbench_withdraw_pending_fuse_request() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(&mut context, &mut contract, None);
+        workbench_deposit(&mut context, &mut contract, &["1:1", "2:1"]);
+        contract.nft_commit_fuse("3".to_string(), loot_salt_hash());
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.nft_fuse_workbench_withdraw("3".to_string(), None);
+    }
+
+    #[test]
+    fn test_nft_refund_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(&mut context, &mut contract, None);
+        contract.change_fuse_requirements(
+            "3".to_string(),
+            contract.nft_get_series_single("3".to_string()).fuse_requirements,
+            fuse_cost(&[(NEAR_TOKEN_ID, 1_000)]),
+        );
+        workbench_deposit(&mut context, &mut contract, &["1:1", "2:1"]);
+        testing_env!(context.attached_deposit(1_000 + STORAGE_FOR_MINT).build());
+        contract.nft_commit_fuse("3".to_string(), loot_salt_hash());
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_index(REQUEST_EXPIRY_BLOCKS + 1)
+            .build());
+        contract.nft_refund_fuse("3".to_string());
+        assert!(contract.nft_get_fuse_request(accounts(2), "3".to_string()).is_none());
+        let near_transfers = near_transfers();
+        assert!(near_transfers.contains(&(accounts(2).to_string(), 1_000)));
+        // the storage freed by the request
+        assert!(near_transfers
+            .iter()
+            .any(|(receiver_id, deposit)| *receiver_id == accounts(2).to_string() && *deposit > 1_000));
+        assert_eq!(
+            contract.nft_get_fuse_workbench(accounts(2), "3".to_string()),
+            vec!["1:1".to_string(), "2:1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_ft_on_transfer_commit_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(&mut context, &mut contract, None);
+        contract.change_fuse_requirements(
+            "3".to_string(),
+            contract.nft_get_series_single("3".to_string()).fuse_requirements,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+        workbench_deposit(&mut context, &mut contract, &["1:1", "2:1"]);
+        storage_deposit(&mut context, &mut contract);
+
+        let msg = json!({
+            "action": "commit_fuse",
+            "target_token_series_id": "3",
+            "salt_hash": loot_salt_hash(),
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 150, msg), 50);
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 0);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .block_index(1)
+            .build());
+        contract.nft_reveal_fuse("3".to_string(), LOOT_SALT.to_string());
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 100);
+    }
+
+    #[test]
+    fn test_nft_fuse_multiple_outputs() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+        create_series(&mut contract, &HashMap::new(), None, Some(2));
+
+        let mut fuse_requirement = recipe(&["1", "2"]);
+        fuse_requirement.outputs = Some(vec![
+            FuseOutput {
+                token_series_id: "3".to_string(),
+                quantity: 1,
+            },
+            FuseOutput {
+                token_series_id: "4".to_string(),
+                quantity: 2,
+            },
+        ]);
+        contract.change_fuse_requirements("3".to_string(), Some(vec![fuse_requirement]), None);
+
+        // one more token is minted than burned
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+
+        let fuse_outcome = contract.nft_fuse(token_ids, "3".to_string(), None);
+        let minted = vec!["3:1".to_string(), "4:1".to_string(), "4:2".to_string()];
+        assert_eq!(fuse_outcome.token_ids, minted);
+        assert_eq!(contract.get_last_tokens_by_owner(accounts(2)), minted);
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "4:2".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Token series 4 supply maxed")]
+    fn test_invalid_nft_fuse_outputs_above_copies() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, None);
+        create_series(&mut contract, &HashMap::new(), None, Some(1));
+
+        let mut fuse_requirement = recipe(&["1", "2"]);
+        fuse_requirement.outputs = Some(vec![FuseOutput {
+            token_series_id: "4".to_string(),
+            quantity: 2,
+        }]);
+        contract.change_fuse_requirements("3".to_string(), Some(vec![fuse_requirement]), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: ft_token_id dai not approved")]
+    fn test_invalid_fuse_cost_not_approved() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+
+        contract.change_fuse_requirements(
+            "3".to_string(),
+            Some(vec![recipe(&["1", "2"])]),
+            fuse_cost(&[("dai", 100)]),
+        );
+    }
+
+    /// accounts(2) deposits storage for a mint
+    fn storage_deposit(context: &mut VMContextBuilder, contract: &mut Contract) {
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        contract.storage_deposit(None);
+    }
+
+    fn ft_fuse_msg(token_ids: Vec<TokenId>) -> String {
+        json!({
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_ft_on_transfer_exact_amount() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, ft_fuse_msg(token_ids)), 0);
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "3:1".to_string());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_excess() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 150, ft_fuse_msg(token_ids)), 50);
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "3:1".to_string());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_underpaid() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        assert_eq!(
+            ft_transfer_call(&mut context, &mut contract, 99, ft_fuse_msg(token_ids.clone())),
+            99
+        );
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+        assert!(contract.get_last_tokens_by_owner(accounts(2)).is_empty());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_failed_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        assert_eq!(
+            ft_transfer_call(
+                &mut context,
+                &mut contract,
+                100,
+                ft_fuse_msg(vec![token_ids[0].clone()])
+            ),
+            100
+        );
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_tagged_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "action": "fuse",
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 0);
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "3:1".to_string());
+    }
+
+    fn ft_transfer_call(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        amount: u128,
+        msg: String,
+    ) -> u128 {
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build());
+
+        match contract.ft_on_transfer(accounts(2), U128::from(amount), msg) {
+            PromiseOrValue::Value(unused_amount) => unused_amount.0,
+            PromiseOrValue::Promise(_) => panic!("ft_on_transfer returned a promise"),
+        }
+    }
+
+    /// series 1 sells for 100 of accounts(4), accounts(1) is the treasury
+    fn setup_ft_buy(context: &mut VMContextBuilder, contract: &mut Contract) {
+        storage_deposit(context, contract);
+        setup_priced_series(context, contract);
+        contract.treasury_id = accounts(1).to_string();
+        contract.add_approved_ft_token_ids(vec![accounts(4)]);
+        contract.nft_set_series_ft_price(
+            "1".to_string(),
+            Some(FtPrice {
+                ft_contract_id: accounts(4).to_string(),
+                price: U128::from(1_000),
+            }),
+        );
+    }
+
+    fn ft_buy_msg() -> String {
+        json!({
+            "action": "buy",
+            "token_series_id": "1",
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_ft_buy() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 1_500, ft_buy_msg()), 500);
+        assert_eq!(
+            contract.nft_token("1:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(1)).0, 50);
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 950);
+        assert_eq!(contract.nft_purchases_by_account("1".to_string(), accounts(2)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: storage deposit of 0 does not cover")]
+    fn test_invalid_ft_buy_no_storage_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.storage_withdraw(None);
+
+        ft_transfer_call(&mut context, &mut contract, 1_000, ft_buy_msg());
+    }
+
+    #[test]
+    fn test_storage_deposit_withdraw() {
+        let (mut context, mut contract) = setup_contract();
+        storage_deposit(&mut context, &mut contract);
+        let balance = contract.storage_balance_of(accounts(2)).0;
+        assert!(balance > 0 && balance < STORAGE_FOR_MINT);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        assert_eq!(contract.storage_withdraw(Some(U128(balance - 1))).0, 1);
+        assert_eq!(contract.storage_balance_of(accounts(2)).0, 1);
+    }
+
+    #[test]
+    fn test_ft_buy_allowlist_price() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        contract.nft_add_series_allowlist("1".to_string(), vec![accounts(2)]);
+        testing_env!(context.attached_deposit(1).build());
+        contract.nft_set_series_sale_config(
+            "1".to_string(),
+            Some(SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: None,
+                allowlist_price: None,
+                allowlist_ft_price: Some(U128::from(600)),
+            }),
+        );
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 1_500, ft_buy_msg()), 900);
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 570);
+    }
+    #[test]
+    fn test_ft_buy_refund_price_curve() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+        contract.nft_set_series_price_curve("1".to_string(), Some(dutch_auction()));
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 1_000, ft_buy_msg()), 1_000);
+        assert!(contract.nft_token("1:1".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_ft_buy_refund_underpaid() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 999, ft_buy_msg()), 999);
+        assert!(contract.nft_token("1:1".to_string()).is_none());
+        assert_eq!(contract.nft_purchases_by_account("1".to_string(), accounts(2)), 0);
+    }
+
+    #[test]
+    fn test_ft_buy_refund_invalid_msg() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+
+        let msg = json!({"action": "sell", "token_series_id": "1"}).to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 1_000, msg), 1_000);
+        assert!(contract.nft_token("1:1".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_upgrade() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+        contract.change_fuse_requirements(
+            "3".to_string(),
+            Some(vec![recipe(&["1"])]),
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+        storage_deposit(&mut context, &mut contract);
+
+        let msg = json!({
+            "version": 1,
+            "action": "upgrade",
+            "token_id": token_ids[0],
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 0);
+        assert!(contract.nft_token(token_ids[0].clone()).is_none());
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "3:1".to_string());
+    }
+
+    fn loot_table(entries: &[(&str, u32)]) -> Vec<LootEntry> {
+        entries
+            .iter()
+            .map(|(token_series_id, weight)| LootEntry {
+                token_series_id: token_series_id.to_string(),
+                weight: *weight,
+            })
+            .collect()
+    }
+
+    fn loot_box(loot_table: Vec<LootEntry>) -> LootBox {
+        LootBox {
+            loot_table,
+            price: Some(U128(0)),
+            ft_price: None,
+            supply: None,
+            sale_starts_at: None,
+            sale_ends_at: None,
+            opened: 0,
+            per_account_cap: None,
+            key: None,
+            pity: None,
+        }
+    }
+
+    /// series 1 with 1 copy and series 2 unlimited in a 9000/1000 loot box "box"
+    fn setup_random_loot(context: &mut VMContextBuilder, contract: &mut Contract) {
+        setup_series(context, contract, &[(None, Some(1)), (None, None)], STORAGE_FOR_MINT);
+        contract.set_nft_loot_box(
+            "box".to_string(),
+            loot_box(loot_table(&[("1", 9_000), ("2", 1_000)])),
+        );
+    }
+
+    const LOOT_SALT: &str = "salt";
+
+    fn loot_salt_hash() -> Base64VecU8 {
+        Base64VecU8::from(env::sha256(LOOT_SALT.as_bytes()))
+    }
+
+    fn loot_random_seed(value: u64) -> Vec<u8> {
+        let mut random_seed = value.to_le_bytes().to_vec();
+        random_seed.resize(32, 0);
+        random_seed
+    }
+
+    /// first random_seed whose reveal of loot_request_id rolls token_series_id
+    fn loot_random_seed_for(
+        loot_table: &[LootEntry],
+        is_available: &[bool],
+        loot_request_id: LootRequestId,
+        token_series_id: &str,
+    ) -> Vec<u8> {
+        (0..1_000)
+            .map(loot_random_seed)
+            .find(|random_seed| {
+                let reveal_seed = reveal_seed(random_seed, LOOT_SALT, loot_request_id);
+                roll_loot_table(loot_table, is_available, &reveal_seed).unwrap().token_series_id
+                    == token_series_id
+            })
+            .unwrap()
+    }
+
+    /// commits with the current context, then reveals in the next block with random_seed
+    fn open_loot_box(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        loot_box_id: &str,
+        key_token_id: Option<&str>,
+        random_seed: Vec<u8>,
+    ) -> TokenId {
+        let loot_request_id = contract.nft_commit_random_loot(
+            loot_box_id.to_string(),
+            loot_salt_hash(),
+            key_token_id.map(|key_token_id| key_token_id.to_string()),
+        );
+
+        let attached_deposit = context.context.attached_deposit;
+        let block_index = context.context.block_index;
+        testing_env!(context
+            .attached_deposit(STORAGE_FOR_MINT)
+            .block_index(block_index + 1)
+            .random_seed(random_seed)
+            .build());
+        let token_id = contract.nft_reveal_random_loot(loot_request_id, LOOT_SALT.to_string());
+
+        testing_env!(context.attached_deposit(attached_deposit).build());
+        token_id
+    }
+
+    #[test]
+    fn test_random_loot_weighted() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        let random_loot_box = contract.nft_get_loot_box("box".to_string()).unwrap();
+        assert_eq!(random_loot_box.odds[0].odds, 9_000);
+        assert_eq!(random_loot_box.odds[1].odds, 1_000);
+
+        let loot_table = random_loot_box.loot_box.loot_table;
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let random_seed = loot_random_seed_for(&loot_table, &[true, true], 0, "2");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "box", None, random_seed),
+            "2:1".to_string()
+        );
+        let random_seed = loot_random_seed_for(&loot_table, &[true, true], 1, "1");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "box", None, random_seed),
+            "1:1".to_string()
+        );
+    }
+
+    #[test]
+    fn test_random_loot_skips_capped_series() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let loot_table = loot_table(&[("1", 9_000), ("2", 1_000)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let random_seed = loot_random_seed_for(&loot_table, &[true, true], 0, "1");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "box", None, random_seed),
+            "1:1".to_string()
+        );
+
+        let odds = contract.nft_get_loot_box("box".to_string()).unwrap().odds;
+        assert!(!odds[0].is_available);
+        assert_eq!(odds[0].odds, 0);
+        assert_eq!(odds[1].odds, 10_000);
+
+        let random_seed = loot_random_seed_for(&loot_table, &[true, true], 1, "1");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "box", None, random_seed),
+            "2:1".to_string()
+        );
+    }
+
+    #[test]
+    fn test_random_loot_skips_series_closed_to_opener() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let loot_table = loot_table(&[("1", 9_000), ("2", 1_000)]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.nft_set_series_sale_config(
+            "1".to_string(),
+            Some(SaleConfig {
+                phase: SalePhase::Allowlist,
+                per_wallet_cap: None,
+                allowlist_price: None,
+                allowlist_ft_price: None,
+            }),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let random_seed = loot_random_seed_for(&loot_table, &[true, true], 0, "1");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "box", None, random_seed),
+            "2:1".to_string()
+        );
+    }
+
+    #[test]
+    fn test_random_loot_box_supply() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut limited_box = loot_box(loot_table(&[("2", 10_000)]));
+        limited_box.supply = Some(1);
+        contract.set_nft_loot_box("limited".to_string(), limited_box);
+
+        assert_eq!(contract.nft_get_active_loot_boxes().len(), 2);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "limited", None, loot_random_seed(0)),
+            "2:1".to_string()
+        );
+
+        let limited_box = contract.nft_get_loot_box("limited".to_string()).unwrap();
+        assert_eq!(limited_box.loot_box.opened, 1);
+        assert_eq!(limited_box.sale_status, SaleStatus::Ended);
+        let active_loot_boxes = contract.nft_get_active_loot_boxes();
+        assert_eq!(active_loot_boxes.len(), 1);
+        assert_eq!(active_loot_boxes[0].loot_box_id, "box".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot box sold out")]
+    fn test_invalid_random_loot_box_sold_out() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut limited_box = loot_box(loot_table(&[("2", 10_000)]));
+        limited_box.supply = Some(1);
+        contract.set_nft_loot_box("limited".to_string(), limited_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        open_loot_box(&mut context, &mut contract, "limited", None, loot_random_seed(0));
+        open_loot_box(&mut context, &mut contract, "limited", None, loot_random_seed(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot box not open yet")]
+    fn test_invalid_random_loot_box_upcoming() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut upcoming_box = loot_box(loot_table(&[("2", 10_000)]));
+        upcoming_box.sale_starts_at = Some(U64(1_000));
+        contract.set_nft_loot_box("upcoming".to_string(), upcoming_box);
+        assert!(contract.nft_get_active_loot_boxes().iter().all(|loot_box| loot_box.loot_box_id != "upcoming"));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .block_timestamp(999)
+            .build());
+        open_loot_box(&mut context, &mut contract, "upcoming", None, loot_random_seed(0));
+    }
+
+    #[test]
+    fn test_random_loot_box_price() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut paid_box = loot_box(loot_table(&[("2", 10_000)]));
+        paid_box.price = Some(U128::from(1 * 10u128.pow(24)));
+        contract.set_nft_loot_box("paid".to_string(), paid_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1 * 10u128.pow(24) + STORAGE_FOR_MINT)
+            .build());
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "paid", None, loot_random_seed(0)),
+            "2:1".to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: attached deposit is less than price : 1000000000000000000000000")]
+    fn test_invalid_random_loot_box_price() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut paid_box = loot_box(loot_table(&[("2", 10_000)]));
+        paid_box.price = Some(U128::from(1 * 10u128.pow(24)));
+        contract.set_nft_loot_box("paid".to_string(), paid_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        open_loot_box(&mut context, &mut contract, "paid", None, loot_random_seed(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot box has no NEAR price")]
+    fn test_invalid_random_loot_box_ft_price_only() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        contract.add_approved_ft_token_ids(vec![accounts(4)]);
+        let mut ft_box = loot_box(loot_table(&[("2", 10_000)]));
+        ft_box.price = None;
+        ft_box.ft_price = Some(FtPrice {
+            ft_contract_id: accounts(4).to_string(),
+            price: U128::from(100),
+        });
+        contract.set_nft_loot_box("ft".to_string(), ft_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        open_loot_box(&mut context, &mut contract, "ft", None, loot_random_seed(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot box cap of 1 reached")]
+    fn test_invalid_random_loot_box_per_account_cap() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+        let mut capped_box = loot_box(loot_table(&[("2", 10_000)]));
+        capped_box.per_account_cap = Some(1);
+        contract.set_nft_loot_box("capped".to_string(), capped_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        open_loot_box(&mut context, &mut contract, "capped", None, loot_random_seed(0));
+        assert_eq!(contract.nft_loot_box_openings("capped".to_string(), accounts(2)), 1);
+        open_loot_box(&mut context, &mut contract, "capped", None, loot_random_seed(0));
+    }
+
+    /// loot box "locked" of series 2 behind a key of series 3, accounts(2) owns key 3:1
+    fn setup_loot_box_key(context: &mut VMContextBuilder, contract: &mut Contract, burn: bool) {
+        setup_random_loot(context, contract);
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(contract, &royalty, None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        contract.nft_mint("3".to_string(), accounts(2));
+
+        let mut locked_box = loot_box(loot_table(&[("2", 10_000)]));
+        locked_box.key = Some(LootBoxKey {
+            token_series_id: "3".to_string(),
+            burn,
+        });
+        contract.set_nft_loot_box("locked".to_string(), locked_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+    }
+
+    #[test]
+    fn test_random_loot_box_hold_key() {
+        let (mut context, mut contract) = setup_contract();
+        setup_loot_box_key(&mut context, &mut contract, false);
+
+        open_loot_box(&mut context, &mut contract, "locked", Some("3:1"), loot_random_seed(0));
+        open_loot_box(&mut context, &mut contract, "locked", Some("3:1"), loot_random_seed(0));
+        assert_eq!(contract.nft_supply_for_series("2".to_string()).0, 2);
+        assert!(contract.nft_token("3:1".to_string()).is_some());
+    }
+
+    #[test]
+    fn test_random_loot_box_burn_key() {
+        let (mut context, mut contract) = setup_contract();
+        setup_loot_box_key(&mut context, &mut contract, true);
+
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "locked", Some("3:1"), loot_random_seed(0)),
+            "2:1".to_string()
+        );
+        assert!(contract.nft_token("3:1".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot box locked requires a key of series 3")]
+    fn test_invalid_random_loot_box_without_key() {
+        let (mut context, mut contract) = setup_contract();
+        setup_loot_box_key(&mut context, &mut contract, true);
+
+        open_loot_box(&mut context, &mut contract, "locked", None, loot_random_seed(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: key token_id 3:1 not owned by")]
+    fn test_invalid_random_loot_box_key_of_other_account() {
+        let (mut context, mut contract) = setup_contract();
+        setup_loot_box_key(&mut context, &mut contract, false);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        open_loot_box(&mut context, &mut contract, "locked", Some("3:1"), loot_random_seed(0));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_random_loot() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        let mut ft_box = loot_box(loot_table(&[("1", 5_000), ("2", 5_000)]));
+        ft_box.ft_price = Some(FtPrice {
+            ft_contract_id: accounts(4).to_string(),
+            price: U128::from(100),
+        });
+        contract.set_nft_loot_box("box".to_string(), ft_box);
+        storage_deposit(&mut context, &mut contract);
+
+        let msg = json!({
+            "action": "random_loot",
+            "loot_box_id": "box",
+            "salt_hash": loot_salt_hash(),
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 150, msg), 50);
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 0);
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .block_index(1)
+            .build());
+        contract.nft_reveal_random_loot(0, LOOT_SALT.to_string());
+        assert_eq!(
+            contract.nft_supply_for_series("1".to_string()).0
+                + contract.nft_supply_for_series("2".to_string()).0,
+            3
+        );
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot request can be revealed from the next block")]
+    fn test_invalid_reveal_random_loot_same_block() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let loot_request_id = contract.nft_commit_random_loot("box".to_string(), loot_salt_hash(), None);
+        contract.nft_reveal_random_loot(loot_request_id, LOOT_SALT.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: salt does not match salt_hash")]
+    fn test_invalid_reveal_random_loot_salt() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let loot_request_id = contract.nft_commit_random_loot("box".to_string(), loot_salt_hash(), None);
+        testing_env!(context.block_index(1).build());
+        contract.nft_reveal_random_loot(loot_request_id, "pepper".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot request expired")]
+    fn test_invalid_reveal_random_loot_expired() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let loot_request_id = contract.nft_commit_random_loot("box".to_string(), loot_salt_hash(), None);
+        testing_env!(context.block_index(REQUEST_EXPIRY_BLOCKS + 1).build());
+        contract.nft_reveal_random_loot(loot_request_id, LOOT_SALT.to_string());
+    }
+
+    /// loot box "pity" of series 2 and rare series 3, guaranteed after 2 openings without it
+    fn setup_loot_pity(context: &mut VMContextBuilder, contract: &mut Contract) -> Vec<LootEntry> {
+        setup_random_loot(context, contract);
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(STORAGE_FOR_CREATE_SERIES)
+            .build());
+        let royalty: HashMap<AccountId, u32> = HashMap::new();
+        create_series(contract, &royalty, None, None);
+
+        let pity_table = loot_table(&[("2", 9_000), ("3", 1_000)]);
+        let mut pity_box = loot_box(pity_table.clone());
+        pity_box.pity = Some(LootPity {
+            token_series_id: "3".to_string(),
+            after: 2,
+        });
+        contract.set_nft_loot_box("pity".to_string(), pity_box);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        pity_table
+    }
+
+    #[test]
+    fn test_random_loot_pity() {
+        let (mut context, mut contract) = setup_contract();
+        let pity_table = setup_loot_pity(&mut context, &mut contract);
+
+        for loot_request_id in 0..2 {
+            let random_seed = loot_random_seed_for(&pity_table, &[true, true], loot_request_id, "2");
+            open_loot_box(&mut context, &mut contract, "pity", None, random_seed);
+            assert_eq!(
+                contract.nft_loot_pity_counter("pity".to_string(), accounts(2)),
+                loot_request_id as u32 + 1
+            );
+        }
+
+        let random_seed = loot_random_seed_for(&pity_table, &[true, true], 2, "2");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "pity", None, random_seed),
+            "3:1".to_string()
+        );
+        assert_eq!(contract.nft_loot_pity_counter("pity".to_string(), accounts(2)), 0);
+    }
+
+    #[test]
+    fn test_random_loot_pity_resets_on_drop() {
+        let (mut context, mut contract) = setup_contract();
+        let pity_table = setup_loot_pity(&mut context, &mut contract);
+
+        let random_seed = loot_random_seed_for(&pity_table, &[true, true], 0, "2");
+        open_loot_box(&mut context, &mut contract, "pity", None, random_seed);
+        assert_eq!(contract.nft_loot_pity_counter("pity".to_string(), accounts(2)), 1);
+
+        let random_seed = loot_random_seed_for(&pity_table, &[true, true], 1, "3");
+        assert_eq!(
+            open_loot_box(&mut context, &mut contract, "pity", None, random_seed),
+            "3:1".to_string()
+        );
+        assert_eq!(contract.nft_loot_pity_counter("pity".to_string(), accounts(2)), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: pity token series 1 not in loot table")]
+    fn test_invalid_random_loot_pity_series() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        let mut pity_box = loot_box(loot_table(&[("2", 10_000)]));
+        pity_box.pity = Some(LootPity {
+            token_series_id: "1".to_string(),
+            after: 2,
+        });
+        contract.set_nft_loot_box("pity".to_string(), pity_box);
+    }
+
+    #[test]
+    fn test_refund_random_loot() {
+        let (mut context, mut contract) = setup_contract();
+        setup_loot_box_key(&mut context, &mut contract, true);
+
+        let loot_request_id = contract.nft_commit_random_loot(
+            "locked".to_string(),
+            loot_salt_hash(),
+            Some("3:1".to_string()),
+        );
+        assert_eq!(contract.nft_token("3:1".to_string()).unwrap().owner_id, env::current_account_id());
+        assert_eq!(contract.nft_get_loot_box("locked".to_string()).unwrap().loot_box.opened, 1);
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_index(REQUEST_EXPIRY_BLOCKS + 1)
+            .build());
+        contract.nft_refund_random_loot(loot_request_id);
+        assert!(near_transfers()
+            .iter()
+            .any(|(receiver_id, deposit)| *receiver_id == accounts(2).to_string() && *deposit > 1));
+
+        assert!(contract.nft_get_loot_request(loot_request_id).is_none());
+        assert_eq!(contract.nft_token("3:1".to_string()).unwrap().owner_id, accounts(2).to_string());
+        assert_eq!(contract.nft_get_loot_box("locked".to_string()).unwrap().loot_box.opened, 0);
+        assert_eq!(contract.nft_loot_box_openings("locked".to_string(), accounts(2)), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot request not expired")]
+    fn test_invalid_refund_random_loot_not_expired() {
+        let (mut context, mut contract) = setup_contract();
+        setup_random_loot(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        let loot_request_id = contract.nft_commit_random_loot("box".to_string(), loot_salt_hash(), None);
+        testing_env!(context
+            .attached_deposit(1)
+            .block_index(REQUEST_EXPIRY_BLOCKS)
+            .build());
+        contract.nft_refund_random_loot(loot_request_id);
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_unsupported_version() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "version": FT_ON_TRANSFER_MSG_VERSION + 1,
+            "action": "fuse",
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_version_zero() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "version": 0,
+            "action": "fuse",
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_unsupported_version_other_shape() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "version": FT_ON_TRANSFER_MSG_VERSION + 1,
+            "action": "fuse",
+            "token_ids": token_ids,
+            "target_token_series_id": 3,
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+
+        let msg = json!({
+            "version": FT_ON_TRANSFER_MSG_VERSION + 1,
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_unknown_action() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "action": "new_thing",
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_refund_malformed_action() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(
+            &mut context,
+            &mut contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+
+        let msg = json!({
+            "action": "buy",
+            "token_seires_id": "3",
+            "token_ids": token_ids,
+            "target_token_series_id": "3",
+        })
+        .to_string();
+        assert_eq!(ft_transfer_call(&mut context, &mut contract, 100, msg), 100);
+        assert!(contract.nft_token(token_ids[0].clone()).is_some());
+    }
+
+    /// accounts(2) sends token_id to the contract (accounts(0)) with a fuse msg for series 3
+    fn nft_fuse_transfer(context: &mut VMContextBuilder, contract: &mut Contract, token_id: &str) -> bool {
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(0), token_id.to_string(), None, None);
+
+        // nft_transfer_call makes the contract the predecessor of nft_on_transfer
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(0)
+            .build());
+        let msg = json!({"action": "fuse", "target_token_series_id": "3"}).to_string();
+        match contract.nft_on_transfer(
+            accounts(2).to_string(),
+            accounts(2).to_string(),
+            token_id.to_string(),
+            msg,
+        ) {
+            PromiseOrValue::Value(is_returned) => is_returned,
+            PromiseOrValue::Promise(_) => panic!("nft_on_transfer returned a promise"),
+        }
+    }
+
+    #[test]
+    fn test_nft_on_transfer_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        storage_deposit(&mut context, &mut contract);
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "1:1"));
+        assert_eq!(
+            contract.nft_get_fuse_workbench(accounts(2), "3".to_string()),
+            vec!["1:1".to_string()]
+        );
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "2:1"));
+        assert!(contract
+            .nft_get_fuse_workbench(accounts(2), "3".to_string())
+            .is_empty());
+        assert!(contract.nft_token("1:1".to_string()).is_none());
+        assert!(contract.nft_token("2:1".to_string()).is_none());
+        assert_eq!(
+            contract.nft_token("3:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+    }
+
+    #[test]
+    fn test_nft_on_transfer_returns_unfit_token() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        contract.nft_mint("1".to_string(), accounts(2));
+        storage_deposit(&mut context, &mut contract);
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "1:1"));
+        assert!(nft_fuse_transfer(&mut context, &mut contract, "1:2"));
+        assert_eq!(
+            contract.nft_get_fuse_workbench(accounts(2), "3".to_string()),
+            vec!["1:1".to_string()]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: storage deposit of 0 does not cover")]
+    fn test_invalid_nft_on_transfer_no_storage_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+
+        nft_fuse_transfer(&mut context, &mut contract, "1:1");
+    }
+
+    #[test]
+    fn test_nft_on_transfer_keeps_chance_fuse() {
+        let (mut context, mut contract) = setup_contract();
+        setup_chance_fuse(&mut context, &mut contract, None);
+        storage_deposit(&mut context, &mut contract);
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "1:1"));
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "2:1"));
+        assert_eq!(
+            contract.nft_get_fuse_workbench(accounts(2), "3".to_string()),
+            vec!["1:1".to_string(), "2:1".to_string()]
+        );
+        assert!(contract.nft_token("3:1".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_nft_on_transfer_withdraw() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        storage_deposit(&mut context, &mut contract);
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "1:1"));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        assert_eq!(
+            contract.nft_fuse_workbench_withdraw("3".to_string(), None),
+            vec!["1:1".to_string()]
+        );
+        assert_eq!(
+            contract.nft_token("1:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+    }
+
+    #[test]
+    fn test_nft_cancel_fuse_transfer() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        storage_deposit(&mut context, &mut contract);
+
+        assert!(!nft_fuse_transfer(&mut context, &mut contract, "1:1"));
+        assert_eq!(
+            contract.nft_get_pending_fuse_tokens(accounts(2), "3".to_string()),
+            vec!["1:1".to_string()]
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        assert_eq!(
+            contract.nft_cancel_fuse_transfer("3".to_string()),
+            vec!["1:1".to_string()]
+        );
+        assert!(contract
+            .nft_get_pending_fuse_tokens(accounts(2), "3".to_string())
+            .is_empty());
+        assert_eq!(
+            contract.nft_token("1:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+    }
+
+    fn workbench_deposit(context: &mut VMContextBuilder, contract: &mut Contract, token_ids: &[&str]) {
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(STORAGE_FOR_MINT)
+            .build());
+        contract.nft_fuse_workbench_deposit(
+            "3".to_string(),
+            token_ids.iter().map(|token_id| token_id.to_string()).collect(),
+        );
+    }
+
+    #[test]
+    fn test_fuse_workbench() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, fuse_cost(&[(NEAR_TOKEN_ID, 100)]));
+
+        workbench_deposit(&mut context, &mut contract, &["1:1"]);
+        assert_eq!(
+            contract.nft_token("1:1".to_string()).unwrap().owner_id,
+            env::current_account_id()
+        );
+        assert!(contract
+            .nft_tokens_for_owner(accounts(2), None, None)
+            .iter()
+            .flatten()
+            .all(|token| token.token_id != "1:1"));
+
+        workbench_deposit(&mut context, &mut contract, &["2:1"]);
+        let workbenches = contract.nft_get_fuse_workbenches(accounts(2));
+        assert_eq!(
+            workbenches["3"],
+            vec!["1:1".to_string(), "2:1".to_string()]
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(100)
+            .build());
+        let fuse_outcome = contract.nft_fuse_workbench_finalize("3".to_string());
+        assert_eq!(fuse_outcome.token_ids, vec!["3:1".to_string()]);
+        assert!(contract.nft_get_fuse_workbenches(accounts(2)).is_empty());
+        assert_eq!(
+            contract.nft_token("3:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+    }
+
+    #[test]
+    fn test_fuse_workbench_withdraw() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+
+        workbench_deposit(&mut context, &mut contract, &["1:1", "2:1"]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.nft_fuse_workbench_withdraw("3".to_string(), Some(vec!["1:1".to_string()]));
+        assert_eq!(
+            contract.nft_token("1:1".to_string()).unwrap().owner_id,
+            accounts(2).to_string()
+        );
+        assert_eq!(
+            contract.nft_get_fuse_workbench(accounts(2), "3".to_string()),
+            vec!["2:1".to_string()]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: fuse requirements not fulfilled")]
+    fn test_invalid_fuse_workbench_finalize_incomplete() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+
+        workbench_deposit(&mut context, &mut contract, &["1:1"]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.nft_fuse_workbench_finalize("3".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: token_ids do not fit fuse_requirements")]
+    fn test_invalid_fuse_workbench_deposit_unfit() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse(&mut context, &mut contract, None);
+        contract.nft_mint("1".to_string(), accounts(2));
+
+        workbench_deposit(&mut context, &mut contract, &["1:1", "1:2"]);
+    }
+
+    /// fuses with a 100 fuse cost in accounts(4), accounts(1) is the treasury and accounts(0) the creator
+    fn setup_fuse_fees(context: &mut VMContextBuilder, contract: &mut Contract) {
+        let token_ids = setup_fuse(
+            context,
+            contract,
+            fuse_cost(&[(accounts(4).to_string().as_str(), 100)]),
+        );
+        contract.treasury_id = accounts(1).to_string();
+        assert_eq!(ft_transfer_call(context, contract, 150, ft_fuse_msg(token_ids)), 50);
+    }
+
+    #[test]
+    fn test_ft_fuse_collects_fees() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse_fees(&mut context, &mut contract);
+
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(1)).0, 5);
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 95);
+    }
+
+    /// (receiver_id, deposit) of the NEAR transfers made by the last call
+    fn near_transfers() -> Vec<(String, u128)> {
+        near_sdk::test_utils::get_created_receipts()
+            .iter()
+            .filter_map(|receipt| {
+                // storage refunds overflow a JSON u64, read the deposit from the text
+                let receipt = near_sdk::serde_json::to_string(receipt).unwrap();
+                let deposit = receipt.split(r#"{"Transfer":{"deposit":"#).nth(1)?;
+                let deposit = &deposit[..deposit.find('}')?];
+                let receipt: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&receipt).ok()?;
+                Some((receipt["receiver_id"].as_str()?.to_string(), deposit.parse().ok()?))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nft_fuse_pays_near_fees() {
+        let (mut context, mut contract) = setup_contract();
+        let token_ids = setup_fuse(&mut context, &mut contract, fuse_cost(&[(NEAR_TOKEN_ID, 100)]));
+        contract.treasury_id = accounts(1).to_string();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(100)
+            .build());
+        contract.nft_fuse(token_ids, "3".to_string(), None);
+
+        assert_eq!(
+            near_transfers(),
+            vec![(accounts(1).to_string(), 5), (accounts(0).to_string(), 95)]
+        );
+    }
+
+    #[test]
+    fn test_withdraw_fuse_fees() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse_fees(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.withdraw_fuse_fees(accounts(4), None);
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(1)).0, 0);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::default(),
+            near_sdk::RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.resolve_withdraw_fuse_fees(
+            accounts(4).to_string(),
+            accounts(1).to_string(),
+            U128(5)
+        ));
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(1)).0, 0);
+    }
+
+    #[test]
+    fn test_withdraw_fuse_fees_failed_transfer() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse_fees(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.withdraw_fuse_fees(accounts(4), Some(accounts(0)));
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 0);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::default(),
+            near_sdk::RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.resolve_withdraw_fuse_fees(
+            accounts(4).to_string(),
+            accounts(0).to_string(),
+            U128(95)
+        ));
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 95);
+    }
+
+    #[test]
+    fn test_withdraw_sale_proceeds_failed_transfer() {
+        let (mut context, mut contract) = setup_contract();
+        setup_ft_buy(&mut context, &mut contract);
+        ft_transfer_call(&mut context, &mut contract, 1_000, ft_buy_msg());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1)
+            .build());
+        contract.withdraw_sale_proceeds(accounts(4), Some(accounts(0)));
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 0);
+        assert_eq!(contract.get_fuse_fees(accounts(4), accounts(0)).0, 0);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::default(),
+            near_sdk::RuntimeFeesConfig::default(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.resolve_withdraw_sale_proceeds(
+            accounts(4).to_string(),
+            accounts(0).to_string(),
+            U128(950)
+        ));
+        assert_eq!(contract.get_sale_proceeds(accounts(4), accounts(0)).0, 950);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: Owner only")]
+    fn test_invalid_withdraw_fuse_fees_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        setup_fuse_fees(&mut context, &mut contract);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build());
+        contract.withdraw_fuse_fees(accounts(4), None);
+    }
 }
@@ -0,0 +1,429 @@
+use crate::*;
+
+/// sum of LootEntry weights in a loot table
+pub const LOOT_WEIGHT_DENOMINATOR: u32 = 10_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootEntry {
+    pub token_series_id: TokenSeriesId,
+    /// basis points out of LOOT_WEIGHT_DENOMINATOR
+    pub weight: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootOdds {
+    pub token_series_id: TokenSeriesId,
+    pub weight: u32,
+    /// basis points of getting this entry now, 0 when its series can't mint
+    pub odds: u32,
+    pub is_available: bool,
+}
+
+pub type LootBoxId = String;
+
+/// token of token_series_id the opener must hold, or give up when burn is set
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootBoxKey {
+    pub token_series_id: TokenSeriesId,
+    pub burn: bool,
+}
+
+/// guarantees token_series_id after `after` openings of an account without it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootPity {
+    pub token_series_id: TokenSeriesId,
+    pub after: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootBox {
+    pub loot_table: Vec<LootEntry>,
+    /// NEAR price of an opening, None stops NEAR openings, "0" opens for free
+    pub price: Option<U128>,
+    /// price of an opening through ft_transfer_call, None stops FT openings
+    pub ft_price: Option<FtPrice>,
+    /// openings before the box is sold out, unlimited when None
+    pub supply: Option<u64>,
+    /// nanosecond timestamps bounding the openings
+    pub sale_starts_at: Option<U64>,
+    pub sale_ends_at: Option<U64>,
+    /// openings so far, kept when the box is updated
+    #[serde(default)]
+    pub opened: u64,
+    /// openings an account can make, unlimited when None
+    pub per_account_cap: Option<u32>,
+    pub key: Option<LootBoxKey>,
+    pub pity: Option<LootPity>,
+}
+
+impl LootBox {
+    pub(crate) fn is_sold_out(&self) -> bool {
+        self.supply.is_some_and(|supply| self.opened >= supply)
+    }
+
+    pub(crate) fn sale_status(&self) -> SaleStatus {
+        let now = env::block_timestamp();
+        if self.sale_starts_at.is_some_and(|starts_at| now < starts_at.0) {
+            SaleStatus::Upcoming
+        } else if self.is_sold_out() || self.sale_ends_at.is_some_and(|ends_at| now >= ends_at.0) {
+            SaleStatus::Ended
+        } else {
+            SaleStatus::Active
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootBoxJson {
+    pub loot_box_id: LootBoxId,
+    #[serde(flatten)]
+    pub loot_box: LootBox,
+    pub sale_status: SaleStatus,
+    pub odds: Vec<LootOdds>,
+}
+
+pub fn assert_valid_loot_table(loot_table: &[LootEntry]) {
+    assert!(!loot_table.is_empty(), "Skins: loot table is empty");
+    assert!(
+        loot_table.iter().all(|entry| entry.weight > 0),
+        "Skins: loot weight must be positive"
+    );
+    let total_weight: u64 = loot_table.iter().map(|entry| entry.weight as u64).sum();
+    assert_eq!(
+        total_weight, LOOT_WEIGHT_DENOMINATOR as u64,
+        "Skins: loot weights must add up to 10000"
+    );
+}
+
+/// Uniform number below bound from 8 byte chunks of random_seed, rejecting the chunks that would bias the modulo
+pub fn random_below(random_seed: &[u8], bound: u64) -> u64 {
+    assert!(bound > 0, "Skins: bound must be positive");
+    let zone = u64::MAX - (u64::MAX % bound);
+    let mut seed = random_seed.to_vec();
+    loop {
+        for chunk in seed.chunks_exact(8) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(chunk);
+            let value = u64::from_le_bytes(bytes);
+            if value < zone {
+                return value % bound;
+            }
+        }
+        seed = env::sha256(&seed);
+    }
+}
+
+/// Picks an entry by weight among the available ones, None when none is available
+pub fn roll_loot_table<'a>(
+    loot_table: &'a [LootEntry],
+    is_available: &[bool],
+    random_seed: &[u8],
+) -> Option<&'a LootEntry> {
+    let total_weight: u64 = loot_table
+        .iter()
+        .zip(is_available.iter())
+        .filter(|(_, is_available)| **is_available)
+        .map(|(entry, _)| entry.weight as u64)
+        .sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut roll = random_below(random_seed, total_weight);
+    for (entry, is_available) in loot_table.iter().zip(is_available.iter()) {
+        if !is_available {
+            continue;
+        }
+        if roll < entry.weight as u64 {
+            return Some(entry);
+        }
+        roll -= entry.weight as u64;
+    }
+    None
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates or replaces loot_box_id, the opened count of an existing box is kept
+    pub fn set_nft_loot_box(&mut self, loot_box_id: LootBoxId, loot_box: LootBox) -> LootBoxJson {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+
+        assert_valid_loot_table(&loot_box.loot_table);
+        for entry in loot_box.loot_table.iter() {
+            assert!(
+                self.token_series_by_id.get(&entry.token_series_id).is_some(),
+                "Skins: Token series {} not exist",
+                entry.token_series_id
+            );
+        }
+        if let Some(ft_price) = &loot_box.ft_price {
+            assert!(
+                self.approved_ft_token_ids.contains(&ft_price.ft_contract_id),
+                "Skins: ft_token_id {} not approved",
+                ft_price.ft_contract_id
+            );
+        }
+        if let Some(key) = &loot_box.key {
+            assert!(
+                self.token_series_by_id.get(&key.token_series_id).is_some(),
+                "Skins: Token series {} not exist",
+                key.token_series_id
+            );
+        }
+        if let Some(pity) = &loot_box.pity {
+            assert!(pity.after > 0, "Skins: pity after must be positive");
+            assert!(
+                loot_box
+                    .loot_table
+                    .iter()
+                    .any(|entry| entry.token_series_id == pity.token_series_id),
+                "Skins: pity token series {} not in loot table",
+                pity.token_series_id
+            );
+        }
+        if let (Some(starts_at), Some(ends_at)) = (loot_box.sale_starts_at, loot_box.sale_ends_at) {
+            assert!(
+                starts_at.0 < ends_at.0,
+                "Skins: sale_starts_at must be before sale_ends_at"
+            );
+        }
+
+        let mut loot_box = loot_box;
+        loot_box.opened = self
+            .loot_boxes
+            .get(&loot_box_id)
+            .map_or(0, |current_loot_box| current_loot_box.opened);
+        self.loot_boxes.insert(&loot_box_id, &loot_box);
+
+        env::log(
+            json!({
+                "type": "set_nft_loot_box",
+                "params": {
+                    "loot_box_id": loot_box_id,
+                    "loot_box": loot_box,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        self.internal_loot_box_json(loot_box_id, loot_box)
+    }
+
+    pub fn remove_nft_loot_box(&mut self, loot_box_id: LootBoxId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+        self.loot_boxes
+            .remove(&loot_box_id)
+            .expect("Skins: loot box not exist");
+    }
+
+    /// loot box with the odds after skipping series at their copies cap
+    pub fn nft_get_loot_box(&self, loot_box_id: LootBoxId) -> Option<LootBoxJson> {
+        self.loot_boxes
+            .get(&loot_box_id)
+            .map(|loot_box| self.internal_loot_box_json(loot_box_id, loot_box))
+    }
+
+    pub fn nft_get_loot_boxes(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<LootBoxJson> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        self.loot_boxes
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(loot_box_id, loot_box)| self.internal_loot_box_json(loot_box_id, loot_box))
+            .collect()
+    }
+
+    pub fn nft_loot_box_openings(&self, loot_box_id: LootBoxId, account_id: ValidAccountId) -> u32 {
+        self.loot_box_openings
+            .get(&(loot_box_id, account_id.into()))
+            .unwrap_or(0)
+    }
+
+    /// openings of account_id in loot_box_id since its pity token series last dropped
+    pub fn nft_loot_pity_counter(&self, loot_box_id: LootBoxId, account_id: ValidAccountId) -> u32 {
+        self.loot_pity_counters
+            .get(&(loot_box_id, account_id.into()))
+            .unwrap_or(0)
+    }
+
+    /// loot boxes that can be opened now
+    pub fn nft_get_active_loot_boxes(&self) -> Vec<LootBoxJson> {
+        self.loot_boxes
+            .iter()
+            .map(|(loot_box_id, loot_box)| self.internal_loot_box_json(loot_box_id, loot_box))
+            .filter(|loot_box_json| {
+                loot_box_json.sale_status == SaleStatus::Active
+                    && loot_box_json.odds.iter().any(|odds| odds.is_available)
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_check_loot_box_key(
+        &self,
+        loot_box_id: &LootBoxId,
+        key: &LootBoxKey,
+        opener_id: &AccountId,
+        key_token_id: Option<&TokenId>,
+    ) -> Result<(), String> {
+        let key_token_id = key_token_id.ok_or_else(|| {
+            format!(
+                "Skins: loot box {} requires a key of series {}",
+                loot_box_id, key.token_series_id
+            )
+        })?;
+        let key_token_series_id = key_token_id.split(TOKEN_DELIMETER).next().unwrap();
+        if key_token_series_id != key.token_series_id {
+            return Err(format!(
+                "Skins: token_id {} is not a key of loot box {}",
+                key_token_id, loot_box_id
+            ));
+        }
+        if self.tokens.owner_by_id.get(key_token_id).as_ref() != Some(opener_id) {
+            return Err(format!("Skins: key token_id {} not owned by {}", key_token_id, opener_id));
+        }
+        Ok(())
+    }
+
+    fn internal_loot_box_json(&self, loot_box_id: LootBoxId, loot_box: LootBox) -> LootBoxJson {
+        LootBoxJson {
+            loot_box_id,
+            sale_status: loot_box.sale_status(),
+            odds: self.internal_loot_odds(&loot_box.loot_table),
+            loot_box,
+        }
+    }
+
+    /// entries whose series can still mint a token
+    pub(crate) fn internal_loot_availability(&self, loot_table: &[LootEntry]) -> Vec<bool> {
+        loot_table
+            .iter()
+            .map(|entry| {
+                self.internal_check_supply(&[FuseOutput {
+                    token_series_id: entry.token_series_id.clone(),
+                    quantity: 1,
+                }])
+                .is_ok()
+            })
+            .collect()
+    }
+
+    pub(crate) fn internal_loot_odds(&self, loot_table: &[LootEntry]) -> Vec<LootOdds> {
+        let is_available = self.internal_loot_availability(loot_table);
+        let available_weight: u64 = loot_table
+            .iter()
+            .zip(is_available.iter())
+            .filter(|(_, is_available)| **is_available)
+            .map(|(entry, _)| entry.weight as u64)
+            .sum();
+
+        loot_table
+            .iter()
+            .zip(is_available)
+            .map(|(entry, is_available)| LootOdds {
+                token_series_id: entry.token_series_id.clone(),
+                weight: entry.weight,
+                odds: if is_available {
+                    (entry.weight as u64 * LOOT_WEIGHT_DENOMINATOR as u64 / available_weight) as u32
+                } else {
+                    0
+                },
+                is_available,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loot_table() -> Vec<LootEntry> {
+        vec![
+            LootEntry {
+                token_series_id: "1".to_string(),
+                weight: 9_000,
+            },
+            LootEntry {
+                token_series_id: "2".to_string(),
+                weight: 900,
+            },
+            LootEntry {
+                token_series_id: "3".to_string(),
+                weight: 100,
+            },
+        ]
+    }
+
+    fn seed(value: u64) -> Vec<u8> {
+        let mut seed = value.to_le_bytes().to_vec();
+        seed.resize(32, 0);
+        seed
+    }
+
+    #[test]
+    fn test_random_below() {
+        assert_eq!(random_below(&seed(0), 10_000), 0);
+        assert_eq!(random_below(&seed(12_345), 10_000), 2_345);
+    }
+
+    #[test]
+    fn test_random_below_rejects_biased_chunk() {
+        // u64::MAX is in the biased zone for 10_000, the next chunk is used
+        let mut seed = u64::MAX.to_le_bytes().to_vec();
+        seed.extend_from_slice(&42u64.to_le_bytes());
+        assert_eq!(random_below(&seed, 10_000), 42);
+    }
+
+    #[test]
+    fn test_roll_loot_table() {
+        let loot_table = loot_table();
+        let all = vec![true; 3];
+        assert_eq!(roll_loot_table(&loot_table, &all, &seed(0)).unwrap().token_series_id, "1");
+        assert_eq!(roll_loot_table(&loot_table, &all, &seed(8_999)).unwrap().token_series_id, "1");
+        assert_eq!(roll_loot_table(&loot_table, &all, &seed(9_000)).unwrap().token_series_id, "2");
+        assert_eq!(roll_loot_table(&loot_table, &all, &seed(9_999)).unwrap().token_series_id, "3");
+    }
+
+    #[test]
+    fn test_roll_loot_table_skips_unavailable() {
+        let loot_table = loot_table();
+        let is_available = vec![false, true, true];
+        // 1_000 available weight, 900 goes to "2"
+        assert_eq!(
+            roll_loot_table(&loot_table, &is_available, &seed(899)).unwrap().token_series_id,
+            "2"
+        );
+        assert_eq!(
+            roll_loot_table(&loot_table, &is_available, &seed(900)).unwrap().token_series_id,
+            "3"
+        );
+        assert!(roll_loot_table(&loot_table, &[false; 3], &seed(0)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Skins: loot weights must add up to 10000")]
+    fn test_invalid_loot_table_weights() {
+        let mut loot_table = loot_table();
+        loot_table.pop();
+        assert_valid_loot_table(&loot_table);
+    }
+}
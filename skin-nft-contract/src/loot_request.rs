@@ -0,0 +1,360 @@
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+pub type LootRequestId = u64;
+
+/// opening of a loot box paid at commit, rolled at reveal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LootRequest {
+    pub loot_box_id: LootBoxId,
+    pub account_id: AccountId,
+    pub receiver_id: AccountId,
+    /// sha256 of the salt given at reveal
+    pub salt_hash: Base64VecU8,
+    /// held by the contract until the reveal when the loot box key is burned
+    pub key_token_id: Option<TokenId>,
+    /// NEAR when None
+    pub ft_contract_id: Option<AccountId>,
+    pub price: U128,
+    pub block_index: U64,
+}
+
+impl LootRequest {
+    pub(crate) fn is_expired(&self) -> bool {
+        env::block_index() > self.block_index.0 + REQUEST_EXPIRY_BLOCKS
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pays an opening of loot_box_id, the token is rolled by nft_reveal_random_loot in a later block.
+    /// key_token_id is required when the box has a key
+    #[payable]
+    pub fn nft_commit_random_loot(
+        &mut self,
+        loot_box_id: LootBoxId,
+        salt_hash: Base64VecU8,
+        key_token_id: Option<TokenId>,
+    ) -> LootRequestId {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+
+        let price = self
+            .loot_boxes
+            .get(&loot_box_id)
+            .unwrap_or_else(|| env::panic(format!("Skins: loot box {} not exist", loot_box_id).as_bytes()))
+            .price
+            .expect("Skins: loot box has no NEAR price")
+            .0;
+        assert!(
+            env::attached_deposit() >= price,
+            "Skins: attached deposit is less than price : {}",
+            price
+        );
+
+        let loot_request_id = self
+            .internal_commit_random_loot(LootRequest {
+                loot_box_id,
+                account_id: account_id.clone(),
+                receiver_id: account_id,
+                salt_hash,
+                key_token_id,
+                ft_contract_id: None,
+                price: U128(price),
+                block_index: U64(env::block_index()),
+            })
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, price);
+        loot_request_id
+    }
+
+    /// Mints the token of loot_request_id, attach the storage of the minted token
+    #[payable]
+    pub fn nft_reveal_random_loot(&mut self, loot_request_id: LootRequestId, salt: String) -> TokenId {
+        let initial_storage_usage = env::storage_usage();
+
+        let token_id = self
+            .internal_reveal_random_loot(loot_request_id, &salt)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        // the removed loot request can free more storage than the mint takes
+        refund_deposit(
+            env::storage_usage().saturating_sub(initial_storage_usage),
+            0,
+        );
+        token_id
+    }
+
+    /// Gives back the price and the key of an expired loot request
+    #[payable]
+    pub fn nft_refund_random_loot(&mut self, loot_request_id: LootRequestId) {
+        assert_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+
+        let loot_request = self
+            .loot_requests
+            .get(&loot_request_id)
+            .expect("Skins: loot request not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            loot_request.account_id,
+            "Skins: loot request owner only"
+        );
+        assert!(loot_request.is_expired(), "Skins: loot request not expired");
+
+        self.loot_requests.remove(&loot_request_id);
+        if let Some(mut loot_box) = self.loot_boxes.get(&loot_request.loot_box_id) {
+            loot_box.opened = loot_box.opened.saturating_sub(1);
+            self.loot_boxes.insert(&loot_request.loot_box_id, &loot_box);
+        }
+        let openings_key = (loot_request.loot_box_id.clone(), loot_request.account_id.clone());
+        let openings = self.loot_box_openings.get(&openings_key).unwrap_or(0);
+        self.loot_box_openings
+            .insert(&openings_key, &openings.saturating_sub(1));
+
+        if let Some(key_token_id) = &loot_request.key_token_id {
+            self.internal_return_tokens(&loot_request.account_id, std::slice::from_ref(key_token_id));
+        }
+        if loot_request.price.0 > 0 {
+            match &loot_request.ft_contract_id {
+                Some(ft_contract_id) => {
+                    self.internal_send_sale_proceeds(
+                        ft_contract_id.clone(),
+                        loot_request.account_id.clone(),
+                        loot_request.price.0,
+                        "Skins: loot request refund",
+                    );
+                }
+                None => {
+                    Promise::new(loot_request.account_id.clone()).transfer(loot_request.price.0);
+                }
+            }
+        }
+        refund_freed_storage(&loot_request.account_id, initial_storage_usage);
+
+        env::log(
+            json!({
+                "type": "nft_refund_random_loot",
+                "params": {
+                    "loot_request_id": U64(loot_request_id),
+                    "loot_request": loot_request,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    pub fn nft_get_loot_request(&self, loot_request_id: LootRequestId) -> Option<LootRequest> {
+        self.loot_requests.get(&loot_request_id)
+    }
+}
+
+impl Contract {
+    /// Checks the loot box and takes the opening, returns Err before any state change
+    pub(crate) fn internal_commit_random_loot(
+        &mut self,
+        mut loot_request: LootRequest,
+    ) -> Result<LootRequestId, String> {
+        let loot_box_id = loot_request.loot_box_id.clone();
+        let account_id = loot_request.account_id.clone();
+
+        let mut loot_box = self
+            .loot_boxes
+            .get(&loot_box_id)
+            .ok_or_else(|| format!("Skins: loot box {} not exist", loot_box_id))?;
+        if loot_box.is_sold_out() {
+            return Err("Skins: loot box sold out".to_string());
+        }
+        match loot_box.sale_status() {
+            SaleStatus::Upcoming => return Err("Skins: loot box not open yet".to_string()),
+            SaleStatus::Ended => return Err("Skins: loot box ended".to_string()),
+            _ => {}
+        }
+
+        let openings_key = (loot_box_id.clone(), account_id.clone());
+        let openings = self.loot_box_openings.get(&openings_key).unwrap_or(0);
+        if let Some(per_account_cap) = loot_box.per_account_cap {
+            if openings >= per_account_cap {
+                return Err(format!("Skins: loot box cap of {} reached", per_account_cap));
+            }
+        }
+
+        match &loot_box.key {
+            Some(key) => {
+                self.internal_check_loot_box_key(
+                    &loot_box_id,
+                    key,
+                    &account_id,
+                    loot_request.key_token_id.as_ref(),
+                )?;
+                if !key.burn {
+                    loot_request.key_token_id = None;
+                }
+            }
+            None => loot_request.key_token_id = None,
+        }
+
+        if let Some(key_token_id) = &loot_request.key_token_id {
+            let contract_id = env::current_account_id();
+            self.tokens
+                .internal_transfer(&account_id, &contract_id, key_token_id, None, None);
+            NearEvent::log_nft_transfer(
+                account_id.clone(),
+                contract_id,
+                vec![key_token_id.clone()],
+                None,
+                None,
+            );
+        }
+        self.loot_box_openings.insert(&openings_key, &(openings + 1));
+        loot_box.opened += 1;
+        self.loot_boxes.insert(&loot_box_id, &loot_box);
+
+        let loot_request_id = self.next_loot_request_id;
+        self.next_loot_request_id += 1;
+        self.loot_requests.insert(&loot_request_id, &loot_request);
+
+        env::log(
+            json!({
+                "type": "nft_commit_random_loot",
+                "params": {
+                    "loot_request_id": U64(loot_request_id),
+                    "loot_request": loot_request,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        Ok(loot_request_id)
+    }
+
+    /// Rolls and mints loot_request_id for the caller, returns Err before any state change
+    pub(crate) fn internal_reveal_random_loot(
+        &mut self,
+        loot_request_id: LootRequestId,
+        salt: &str,
+    ) -> Result<TokenId, String> {
+        let loot_request = self
+            .loot_requests
+            .get(&loot_request_id)
+            .ok_or_else(|| "Skins: loot request not exist".to_string())?;
+        if env::predecessor_account_id() != loot_request.account_id {
+            return Err("Skins: loot request owner only".to_string());
+        }
+        if env::block_index() <= loot_request.block_index.0 {
+            return Err("Skins: loot request can be revealed from the next block".to_string());
+        }
+        if loot_request.is_expired() {
+            return Err("Skins: loot request expired".to_string());
+        }
+        if env::sha256(salt.as_bytes()) != loot_request.salt_hash.0 {
+            return Err("Skins: salt does not match salt_hash".to_string());
+        }
+
+        let loot_box = self
+            .loot_boxes
+            .get(&loot_request.loot_box_id)
+            .ok_or_else(|| format!("Skins: loot box {} not exist", loot_request.loot_box_id))?;
+        let account_id = &loot_request.account_id;
+        let pity_key = (loot_request.loot_box_id.clone(), account_id.clone());
+        let pity_counter = self.loot_pity_counters.get(&pity_key).unwrap_or(0);
+
+        // entries the opener can't buy are skipped before the roll, a reveal failing after it could be retried
+        let is_available: Vec<bool> = self
+            .internal_loot_availability(&loot_box.loot_table)
+            .into_iter()
+            .zip(loot_box.loot_table.iter())
+            .map(|(is_available, entry)| {
+                is_available
+                    && self
+                        .token_series_by_id
+                        .get(&entry.token_series_id)
+                        .is_some_and(|token_series| {
+                            self.internal_check_purchase(&entry.token_series_id, &token_series, account_id, 1)
+                                .is_ok()
+                        })
+            })
+            .collect();
+        let pity_entry = loot_box.pity.as_ref().and_then(|pity| {
+            loot_box
+                .loot_table
+                .iter()
+                .zip(is_available.iter())
+                .find(|(entry, is_available)| {
+                    **is_available && entry.token_series_id == pity.token_series_id
+                })
+                .filter(|_| pity_counter >= pity.after)
+                .map(|(entry, _)| entry)
+        });
+        let is_pity = pity_entry.is_some();
+        let random_seed = reveal_seed(&env::random_seed(), salt, loot_request_id);
+        let token_series_id = pity_entry
+            .or_else(|| roll_loot_table(&loot_box.loot_table, &is_available, &random_seed))
+            .ok_or_else(|| "Skins: loot box sold out".to_string())?
+            .token_series_id
+            .clone();
+
+        self.loot_requests.remove(&loot_request_id);
+        let pity_counter = loot_box.pity.as_ref().map(|pity| {
+            let pity_counter = if token_series_id == pity.token_series_id {
+                0
+            } else {
+                pity_counter + 1
+            };
+            self.loot_pity_counters.insert(&pity_key, &pity_counter);
+            pity_counter
+        });
+        self.internal_record_purchase(&token_series_id, account_id, 1);
+        if let Some(key_token_id) = &loot_request.key_token_id {
+            self._nft_burn(env::current_account_id(), key_token_id.clone());
+        }
+        let token_id = self._nft_mint_series(token_series_id, loot_request.receiver_id.clone());
+
+        env::log(
+            json!({
+                "type": "nft_reveal_random_loot",
+                "params": {
+                    "loot_request_id": U64(loot_request_id),
+                    "loot_box_id": loot_request.loot_box_id,
+                    "account_id": account_id,
+                    "token_id": token_id,
+                    "is_pity": is_pity,
+                    "pity_counter": pity_counter,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        let price = loot_request.price.0;
+        match &loot_request.ft_contract_id {
+            Some(ft_contract_id) => {
+                let treasury_id = self.treasury_id.clone();
+                self.internal_add_sale_proceeds(ft_contract_id, &treasury_id, price);
+            }
+            None => {
+                if price > 0 {
+                    Promise::new(self.treasury_id.clone()).transfer(price);
+                }
+            }
+        }
+
+        NearEvent::log_nft_mint(
+            loot_request.receiver_id.clone(),
+            vec![token_id.clone()],
+            Some(
+                json!({
+                    "loot_box_id": loot_request.loot_box_id,
+                    "loot_request_id": U64(loot_request_id),
+                    "price": price.to_string(),
+                    "ft_contract_id": loot_request.ft_contract_id,
+                })
+                .to_string(),
+            ),
+        );
+        Ok(token_id)
+    }
+}
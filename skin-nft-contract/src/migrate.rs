@@ -0,0 +1,325 @@
+use crate::*;
+
+/// TokenSeries as stored by the first deployed version
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TokenSeriesV1 {
+    metadata: TokenMetadata,
+    creator_id: AccountId,
+    tokens: UnorderedSet<TokenId>,
+    price: Option<Balance>,
+    is_mintable: bool,
+    royalty: HashMap<AccountId, u32>,
+    fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
+    /// (ft_contract_id, price)
+    fuse_cost: Option<(AccountId, U128)>,
+}
+
+impl From<TokenSeriesV1> for TokenSeries {
+    fn from(token_series: TokenSeriesV1) -> Self {
+        Self {
+            metadata: token_series.metadata,
+            creator_id: token_series.creator_id,
+            tokens: token_series.tokens,
+            price: token_series.price,
+            is_mintable: token_series.is_mintable,
+            royalty: token_series.royalty,
+            fuse_requirements: token_series.fuse_requirements.map(|fuse_requirements| {
+                fuse_requirements
+                    .into_iter()
+                    .map(|token_series_ids| FuseRecipe::from_token_series_ids(token_series_ids).into())
+                    .collect()
+            }),
+            fuse_cost: token_series.fuse_cost.map(|(ft_contract_id, price)| {
+                let mut fuse_cost = FuseCost::new();
+                fuse_cost.insert(ft_contract_id, price);
+                fuse_cost
+            }),
+            treasury_fee: None,
+            sale_config: None,
+            sale_starts_at: None,
+            sale_ends_at: None,
+            price_curve: None,
+            ft_price: None,
+        }
+    }
+}
+
+/// Contract as stored by the first deployed version
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV1 {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    token_series_by_id: UnorderedMap<TokenSeriesId, TokenSeriesV1>,
+    random_loot: Option<Vec<TokenSeriesId>>,
+    last_token_by_owner: LookupMap<AccountId, TokenId>,
+}
+
+/// loot box replacing the random_loot of a ContractV1 deployment
+pub const RANDOM_LOOT_BOX_ID: &str = "random_loot";
+
+/// box rolling the two series of set_nft_random_loot at even odds, closed until the owner sets a price
+fn random_loot_box(token_series_ids: Vec<TokenSeriesId>) -> LootBox {
+    let weight = LOOT_WEIGHT_DENOMINATOR / token_series_ids.len() as u32;
+    let mut loot_table: Vec<LootEntry> = token_series_ids
+        .into_iter()
+        .map(|token_series_id| LootEntry {
+            token_series_id,
+            weight,
+        })
+        .collect();
+    loot_table[0].weight += LOOT_WEIGHT_DENOMINATOR - weight * loot_table.len() as u32;
+
+    LootBox {
+        loot_table,
+        price: None,
+        ft_price: None,
+        supply: None,
+        sale_starts_at: None,
+        sale_ends_at: None,
+        opened: 0,
+        per_account_cap: None,
+        key: None,
+        pity: None,
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Moves the state of a ContractV1 deployment to the current layout
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let mut old_state: ContractV1 = env::state_read().expect("Skins: no state to migrate");
+        let treasury_id = old_state.tokens.owner_id.clone();
+
+        // rewrites the series in place, insert would read the old value as a TokenSeries
+        let mut fuse_targets = vec![];
+        let mut ft_token_ids = vec![];
+        for (token_series_id, token_series) in old_state.token_series_by_id.to_vec() {
+            let token_series = TokenSeries::from(token_series);
+            // fuse costs were only paid in FT, keep them payable
+            ft_token_ids.extend(
+                token_series.fuse_cost.iter().flat_map(|fuse_cost| fuse_cost.keys().cloned()),
+            );
+            fuse_targets.push((
+                token_series_id.clone(),
+                to_fuse_recipes(token_series.fuse_requirements.clone()),
+            ));
+            old_state.token_series_by_id.insert_raw(
+                &token_series_id.try_to_vec().unwrap(),
+                &token_series.try_to_vec().unwrap(),
+            );
+        }
+        // same prefix and entries, only the type of the stored series changes
+        let token_series_by_id = UnorderedMap::try_from_slice(
+            &old_state.token_series_by_id.try_to_vec().unwrap(),
+        )
+        .unwrap();
+
+        let mut contract = Self {
+            tokens: old_state.tokens,
+            metadata: old_state.metadata,
+            token_series_by_id,
+            // LastTokenByOwner holds a single TokenId, get_last_tokens_by_owner still reads it
+            last_tokens_by_owner: LookupMap::new(StorageKey::LastTokensByOwner),
+            fuse_targets_by_ingredient: LookupMap::new(StorageKey::FuseTargetsByIngredient),
+            approved_ft_token_ids: UnorderedSet::new(StorageKey::ApprovedFtTokenIds),
+            treasury_id,
+            fuse_treasury_fee: TREASURY_FEE as u32,
+            fuse_fees: LookupMap::new(StorageKey::FuseFees),
+            max_buy_quantity: DEFAULT_MAX_BUY_QUANTITY,
+            sale_allowlist: LookupSet::new(StorageKey::SaleAllowlist),
+            purchases_by_account: LookupMap::new(StorageKey::PurchasesByAccount),
+            sale_proceeds: LookupMap::new(StorageKey::SaleProceeds),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            fuse_workbenches: LookupMap::new(StorageKey::FuseWorkbenches),
+            loot_boxes: UnorderedMap::new(StorageKey::LootBoxes),
+            loot_box_openings: LookupMap::new(StorageKey::LootBoxOpenings),
+            loot_requests: LookupMap::new(StorageKey::LootRequests),
+            next_loot_request_id: 0,
+            fuse_requests: LookupMap::new(StorageKey::FuseRequests),
+            loot_pity_counters: LookupMap::new(StorageKey::LootPityCounters),
+        };
+
+        for (token_series_id, fuse_requirements) in fuse_targets {
+            contract.internal_add_fuse_targets(&token_series_id, &fuse_requirements);
+        }
+        for ft_token_id in ft_token_ids {
+            contract.approved_ft_token_ids.insert(&ft_token_id);
+        }
+        if let Some(random_loot) = old_state.random_loot {
+            contract
+                .loot_boxes
+                .insert(&RANDOM_LOOT_BOX_ID.to_string(), &random_loot_box(random_loot));
+        }
+
+        contract
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    fn token_series_v1(
+        token_series_id: &str,
+        fuse_requirements: Option<Vec<Vec<TokenSeriesId>>>,
+        fuse_cost: Option<(AccountId, U128)>,
+    ) -> TokenSeriesV1 {
+        TokenSeriesV1 {
+            metadata: TokenMetadata {
+                title: Some(format!("Skin {}", token_series_id)),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                issued_at: None,
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            },
+            creator_id: accounts(0).to_string(),
+            tokens: UnorderedSet::new(StorageKey::TokensBySeriesInner {
+                token_series: token_series_id.to_string(),
+            }),
+            price: None,
+            is_mintable: true,
+            royalty: HashMap::new(),
+            fuse_requirements,
+            fuse_cost,
+        }
+    }
+
+    /// state of a ContractV1 deployment with series 3 fused from 1 and 2
+    fn setup_contract_v1() -> VMContextBuilder {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .build());
+
+        let mut contract_v1 = ContractV1 {
+            tokens: NonFungibleToken::new(
+                StorageKey::NonFungibleToken,
+                accounts(0),
+                Some(StorageKey::TokenMetadata),
+                Some(StorageKey::Enumeration),
+                Some(StorageKey::Approval),
+            ),
+            metadata: LazyOption::new(
+                StorageKey::Metadata,
+                Some(&NFTContractMetadata {
+                    spec: NFT_METADATA_SPEC.to_string(),
+                    name: "Skins Collectible".to_string(),
+                    symbol: "SKINS".to_string(),
+                    icon: None,
+                    base_uri: None,
+                    reference: None,
+                    reference_hash: None,
+                }),
+            ),
+            token_series_by_id: UnorderedMap::new(StorageKey::TokenSeriesById),
+            random_loot: None,
+            last_token_by_owner: LookupMap::new(StorageKey::LastTokenByOwner),
+        };
+        contract_v1
+            .token_series_by_id
+            .insert(&"1".to_string(), &token_series_v1("1", None, None));
+        contract_v1
+            .token_series_by_id
+            .insert(&"2".to_string(), &token_series_v1("2", None, None));
+        contract_v1.token_series_by_id.insert(
+            &"3".to_string(),
+            &token_series_v1(
+                "3",
+                Some(vec![vec!["1".to_string(), "2".to_string()]]),
+                Some((accounts(4).to_string(), U128(100))),
+            ),
+        );
+        contract_v1
+            .last_token_by_owner
+            .insert(&accounts(2).to_string(), &"3:1".to_string());
+        env::state_write(&contract_v1);
+        context
+    }
+
+    #[test]
+    fn test_migrate() {
+        setup_contract_v1();
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_owner(), accounts(0).to_string());
+        assert_eq!(contract.treasury_id, accounts(0).to_string());
+        assert_eq!(contract.nft_get_series_single("1".to_string()).fuse_requirements, None);
+        assert_eq!(contract.nft_get_fuse_targets("1".to_string()), vec!["3".to_string()]);
+        assert_eq!(contract.nft_get_fuse_targets("2".to_string()), vec!["3".to_string()]);
+    }
+
+    #[test]
+    fn test_migrate_fuse_requirements() {
+        setup_contract_v1();
+        let contract = Contract::migrate();
+
+        assert_eq!(
+            contract.nft_get_series_single("3".to_string()).fuse_requirements,
+            Some(vec![FuseRecipe::from_token_series_ids(vec![
+                "1".to_string(),
+                "2".to_string()
+            ])])
+        );
+    }
+
+    #[test]
+    fn test_migrate_last_token_by_owner() {
+        setup_contract_v1();
+        let contract = Contract::migrate();
+
+        assert_eq!(contract.get_last_tokens_by_owner(accounts(2)), vec!["3:1".to_string()]);
+        assert_eq!(contract.get_last_token_by_owner(accounts(2)), "3:1".to_string());
+    }
+
+    #[test]
+    fn test_migrate_random_loot() {
+        setup_contract_v1();
+        let mut contract_v1: ContractV1 = env::state_read().unwrap();
+        contract_v1.random_loot = Some(vec!["1".to_string(), "2".to_string()]);
+        env::state_write(&contract_v1);
+        let contract = Contract::migrate();
+
+        let loot_box = contract.nft_get_loot_box(RANDOM_LOOT_BOX_ID.to_string()).unwrap();
+        assert_eq!(
+            loot_box.loot_box.loot_table,
+            vec![
+                LootEntry {
+                    token_series_id: "1".to_string(),
+                    weight: 5_000,
+                },
+                LootEntry {
+                    token_series_id: "2".to_string(),
+                    weight: 5_000,
+                },
+            ]
+        );
+        assert_eq!(loot_box.loot_box.price, None);
+    }
+
+    #[test]
+    fn test_migrate_fuse_cost() {
+        setup_contract_v1();
+        let contract = Contract::migrate();
+
+        let mut fuse_cost = FuseCost::new();
+        fuse_cost.insert(accounts(4).to_string(), U128(100));
+        assert_eq!(
+            contract.nft_get_series_single("3".to_string()).fuse_cost,
+            Some(fuse_cost)
+        );
+        assert_eq!(contract.get_approved_ft_token_ids(), vec![accounts(4).to_string()]);
+    }
+}
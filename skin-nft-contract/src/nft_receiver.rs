@@ -0,0 +1,116 @@
+use crate::*;
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum NftOnTransferMsg {
+    /// puts the token on the workbench of target_token_series_id, fuses once a fuse_requirement is met
+    Fuse { target_token_series_id: TokenSeriesId },
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// Returns true when token_id goes back to previous_owner_id
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Skins: only accepts tokens of this contract"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        match self.internal_nft_on_transfer(&previous_owner_id, &token_id, &msg) {
+            Ok(()) => {
+                // a panic reverts the action and nft_resolve_transfer returns the token
+                self.internal_charge_storage(
+                    &previous_owner_id,
+                    env::storage_usage().saturating_sub(initial_storage_usage),
+                );
+                PromiseOrValue::Value(false)
+            }
+            Err(err) => {
+                env::log(
+                    json!({
+                        "type": "nft_on_transfer_return",
+                        "params": {
+                            "sender_id": sender_id,
+                            "previous_owner_id": previous_owner_id,
+                            "token_id": token_id,
+                            "reason": err,
+                        }
+                    })
+                    .to_string()
+                    .as_bytes(),
+                );
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sends back the tokens account_id transferred for target_token_series_id that are not fused yet
+    #[payable]
+    pub fn nft_cancel_fuse_transfer(&mut self, target_token_series_id: TokenSeriesId) -> Vec<TokenId> {
+        self.nft_fuse_workbench_withdraw(target_token_series_id, None)
+    }
+
+    pub fn nft_get_pending_fuse_tokens(
+        &self,
+        account_id: ValidAccountId,
+        target_token_series_id: TokenSeriesId,
+    ) -> Vec<TokenId> {
+        self.nft_get_fuse_workbench(account_id, target_token_series_id)
+    }
+}
+
+impl Contract {
+    /// Keeps token_id on the workbench when it fits a fuse_requirement, fuses once one is fulfilled
+    fn internal_nft_on_transfer(
+        &mut self,
+        previous_owner_id: &AccountId,
+        token_id: &TokenId,
+        msg: &str,
+    ) -> Result<(), String> {
+        let NftOnTransferMsg::Fuse {target_token_series_id} = near_sdk::serde_json::from_str(msg)
+            .map_err(|_| "Skins: Not valid nft_on_transfer msg".to_string())?;
+
+        let token_series = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?;
+        let fuse_requirements = to_fuse_recipes(token_series.fuse_requirements)
+            .ok_or_else(|| "Skins: Token series is not fusable".to_string())?;
+        // fuse cost is paid with nft_fuse_workbench_finalize
+        let has_fuse_cost = token_series.fuse_cost.is_some_and(|fuse_cost| !fuse_cost.is_empty());
+
+        self.internal_check_no_fuse_request(previous_owner_id, &target_token_series_id)?;
+        let mut token_ids = self.internal_workbench(previous_owner_id, &target_token_series_id);
+        token_ids.push(token_id.clone());
+
+        if !self.internal_fits_fuse_requirement(&token_ids, &fuse_requirements) {
+            return Err(format!("Skins: token_id {} does not fit fuse_requirements", token_id));
+        }
+
+        // a fuse_requirement with a success_rate is rolled by nft_commit_fuse and nft_reveal_fuse
+        let contract_id = env::current_account_id();
+        let is_fused_now = self
+            .internal_match_fuse_requirement(&token_ids, &fuse_requirements, &contract_id)
+            .is_ok_and(|(idx, _)| fuse_requirements[idx].success_rate.is_none());
+        if has_fuse_cost || !is_fused_now {
+            self.internal_set_workbench(previous_owner_id, &target_token_series_id, token_ids);
+            return Ok(());
+        }
+
+        self.internal_fuse_workbench(previous_owner_id, target_token_series_id, token_ids, None)?;
+        Ok(())
+    }
+}
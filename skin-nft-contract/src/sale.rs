@@ -0,0 +1,530 @@
+use crate::*;
+use crate::fuse_fee::ft_transfer;
+use near_sdk::PromiseResult;
+
+const GAS_FOR_RESOLVE_WITHDRAW_SALE_PROCEEDS: Gas = 10_000_000_000_000;
+
+#[ext_contract(ext_sale_proceeds_resolver)]
+trait SaleProceedsResolver {
+    fn resolve_withdraw_sale_proceeds(
+        &mut self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    ) -> bool;
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SalePhase {
+    /// only allowlisted accounts can buy
+    Allowlist,
+    Public,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleConfig {
+    pub phase: SalePhase,
+    /// tokens an account can get from the series, unlimited when None
+    pub per_wallet_cap: Option<u32>,
+    /// price for allowlisted accounts in the allowlist phase, series price when None
+    pub allowlist_price: Option<U128>,
+    /// allowlist_price in the ft_price token, ft_price when None
+    pub allowlist_ft_price: Option<U128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SaleStatus {
+    /// series has no price
+    NotForSale,
+    Upcoming,
+    Active,
+    Ended,
+}
+
+/// series price in an approved FT, paid with ft_transfer_call
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPrice {
+    pub ft_contract_id: AccountId,
+    pub price: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriceCurve {
+    /// drops linearly from start_price at starts_at to floor_price at ends_at, nanosecond timestamps
+    DutchAuction {
+        start_price: U128,
+        floor_price: U128,
+        starts_at: U64,
+        ends_at: U64,
+    },
+    /// base_price + increment for each token already minted in the series
+    BondingCurve { base_price: U128, increment: U128 },
+}
+
+impl PriceCurve {
+    pub fn assert_valid(&self) {
+        match self {
+            PriceCurve::DutchAuction {
+                start_price,
+                floor_price,
+                starts_at,
+                ends_at,
+            } => {
+                assert!(
+                    start_price.0 >= floor_price.0,
+                    "Skins: start_price must not be lower than floor_price"
+                );
+                assert!(starts_at.0 < ends_at.0, "Skins: starts_at must be before ends_at");
+            }
+            PriceCurve::BondingCurve { .. } => {}
+        }
+    }
+
+    /// price of the next mint when supply tokens are minted at block_timestamp
+    pub fn price_at(&self, supply: u64, block_timestamp: u64) -> Balance {
+        match self {
+            PriceCurve::DutchAuction {
+                start_price,
+                floor_price,
+                starts_at,
+                ends_at,
+            } => {
+                if block_timestamp <= starts_at.0 {
+                    start_price.0
+                } else if block_timestamp >= ends_at.0 {
+                    floor_price.0
+                } else {
+                    let elapsed = (block_timestamp - starts_at.0) as u128;
+                    let duration = (ends_at.0 - starts_at.0) as u128;
+                    start_price.0 - (start_price.0 - floor_price.0) * elapsed / duration
+                }
+            }
+            PriceCurve::BondingCurve {
+                base_price,
+                increment,
+            } => base_price.0 + increment.0 * supply as u128,
+        }
+    }
+}
+
+impl TokenSeries {
+    /// total price of the next quantity mints, price_curve takes precedence over price.
+    /// None when price is unset, which takes the series off sale with or without a curve,
+    /// nft_set_series_price_curve only accepts a curve on a priced series
+    pub(crate) fn quote(&self, quantity: u32) -> Option<Balance> {
+        self.price?;
+        match &self.price_curve {
+            Some(price_curve) => {
+                let supply = self.tokens.len();
+                let now = env::block_timestamp();
+                Some(
+                    (0..quantity as u64)
+                        .map(|offset| price_curve.price_at(supply + offset, now))
+                        .sum(),
+                )
+            }
+            None => self.price.map(|price| price * quantity as u128),
+        }
+    }
+
+    pub(crate) fn sale_status(&self) -> SaleStatus {
+        let now = env::block_timestamp();
+        if self.price.is_none() && self.ft_price.is_none() {
+            SaleStatus::NotForSale
+        } else if self.sale_starts_at.is_some_and(|starts_at| now < starts_at) {
+            SaleStatus::Upcoming
+        } else if self.sale_ends_at.is_some_and(|ends_at| now >= ends_at) {
+            SaleStatus::Ended
+        } else {
+            SaleStatus::Active
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// None removes the sale config, the series goes back to a public sale without cap
+    #[payable]
+    pub fn nft_set_series_sale_config(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        sale_config: Option<SaleConfig>,
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Skins: Creator only"
+        );
+
+        token_series.sale_config = sale_config.clone();
+        self.token_series_by_id
+            .insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_sale_config",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "sale_config": sale_config,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// nanosecond timestamps, nft_buy is open from sale_starts_at until before sale_ends_at
+    #[payable]
+    pub fn nft_set_series_sale_window(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        sale_starts_at: Option<U64>,
+        sale_ends_at: Option<U64>,
+    ) -> SaleStatus {
+        assert_one_yocto();
+
+        let mut token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Skins: Creator only"
+        );
+        if let (Some(starts_at), Some(ends_at)) = (sale_starts_at, sale_ends_at) {
+            assert!(
+                starts_at.0 < ends_at.0,
+                "Skins: sale_starts_at must be before sale_ends_at"
+            );
+        }
+
+        token_series.sale_starts_at = sale_starts_at.map(|starts_at| starts_at.0);
+        token_series.sale_ends_at = sale_ends_at.map(|ends_at| ends_at.0);
+        self.token_series_by_id
+            .insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_sale_window",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "sale_starts_at": sale_starts_at,
+                    "sale_ends_at": sale_ends_at,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+        token_series.sale_status()
+    }
+
+    /// None goes back to the flat series price, the curve only applies while the series has a price
+    #[payable]
+    pub fn nft_set_series_price_curve(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        price_curve: Option<PriceCurve>,
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Skins: Creator only"
+        );
+        if let Some(price_curve) = &price_curve {
+            price_curve.assert_valid();
+            assert!(
+                token_series.price.is_some(),
+                "Skins: series has no price, set a price before a price_curve"
+            );
+        }
+
+        token_series.price_curve = price_curve.clone();
+        self.token_series_by_id
+            .insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_price_curve",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "price_curve": price_curve,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// None stops FT sales of the series
+    #[payable]
+    pub fn nft_set_series_ft_price(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        ft_price: Option<FtPrice>,
+    ) {
+        assert_one_yocto();
+
+        let mut token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Skins: Creator only"
+        );
+        if let Some(ft_price) = &ft_price {
+            assert!(
+                self.approved_ft_token_ids.contains(&ft_price.ft_contract_id),
+                "Skins: ft_token_id {} not approved",
+                ft_price.ft_contract_id
+            );
+        }
+
+        token_series.ft_price = ft_price.clone();
+        self.token_series_by_id
+            .insert(&token_series_id, &token_series);
+
+        env::log(
+            json!({
+                "type": "nft_set_series_ft_price",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "ft_price": ft_price,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+    }
+
+    /// price of the next quantity tokens of token_series_id at the current block, without allowlist discount
+    pub fn nft_get_series_quote(&self, token_series_id: TokenSeriesId, quantity: Option<u32>) -> Option<U128> {
+        self.token_series_by_id
+            .get(&token_series_id)
+            .expect("Token series not exist")
+            .quote(quantity.unwrap_or(1))
+            .map(U128)
+    }
+
+    #[payable]
+    pub fn nft_add_series_allowlist(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        account_ids: Vec<ValidAccountId>,
+    ) {
+        let initial_storage_usage = env::storage_usage();
+        self.internal_assert_series_creator(&token_series_id);
+
+        for account_id in account_ids {
+            self.sale_allowlist
+                .insert(&(token_series_id.clone(), account_id.into()));
+        }
+
+        refund_deposit(env::storage_usage() - initial_storage_usage, 0);
+    }
+
+    #[payable]
+    pub fn nft_remove_series_allowlist(
+        &mut self,
+        token_series_id: TokenSeriesId,
+        account_ids: Vec<ValidAccountId>,
+    ) {
+        assert_one_yocto();
+        self.internal_assert_series_creator(&token_series_id);
+
+        for account_id in account_ids {
+            self.sale_allowlist
+                .remove(&(token_series_id.clone(), account_id.into()));
+        }
+    }
+
+    pub fn nft_is_allowlisted(&self, token_series_id: TokenSeriesId, account_id: ValidAccountId) -> bool {
+        self.sale_allowlist
+            .contains(&(token_series_id, account_id.into()))
+    }
+
+    /// tokens of token_series_id bought or looted by account_id
+    pub fn nft_purchases_by_account(&self, token_series_id: TokenSeriesId, account_id: ValidAccountId) -> u32 {
+        self.purchases_by_account
+            .get(&(token_series_id, account_id.into()))
+            .unwrap_or(0)
+    }
+
+    /// Sends the FT sale proceeds of account_id (treasury by default) in ft_contract_id
+    #[payable]
+    pub fn withdraw_sale_proceeds(
+        &mut self,
+        ft_contract_id: ValidAccountId,
+        account_id: Option<ValidAccountId>,
+    ) -> Promise {
+        assert_one_yocto();
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.tokens.owner_id,
+            "Skins: Owner only"
+        );
+
+        let ft_contract_id: AccountId = ft_contract_id.into();
+        let account_id: AccountId = account_id
+            .map(|account_id| account_id.into())
+            .unwrap_or_else(|| self.treasury_id.clone());
+
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let amount = self.sale_proceeds.remove(&key).unwrap_or(0);
+        assert!(amount > 0, "Skins: no sale proceeds to withdraw");
+
+        self.internal_send_sale_proceeds(ft_contract_id, account_id, amount, "Skins: sale proceeds")
+    }
+
+    /// Puts the amount back to the ledger when ft_transfer failed
+    #[private]
+    pub fn resolve_withdraw_sale_proceeds(
+        &mut self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        let is_success = matches!(env::promise_result(0), PromiseResult::Successful(_));
+
+        if !is_success {
+            self.internal_add_sale_proceeds(&ft_contract_id, &account_id, amount.0);
+        }
+
+        env::log(
+            json!({
+                "type": "withdraw_sale_proceeds",
+                "params": {
+                    "ft_contract_id": ft_contract_id,
+                    "account_id": account_id,
+                    "amount": amount,
+                    "is_success": is_success,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        is_success
+    }
+
+    /// FT paid for series and loot boxes, by (ft_contract_id, account_id)
+    pub fn get_sale_proceeds(&self, ft_contract_id: ValidAccountId, account_id: ValidAccountId) -> U128 {
+        U128(
+            self.sale_proceeds
+                .get(&(ft_contract_id.into(), account_id.into()))
+                .unwrap_or(0),
+        )
+    }
+}
+
+impl Contract {
+    fn internal_assert_series_creator(&self, token_series_id: &TokenSeriesId) {
+        let token_series = self
+            .token_series_by_id
+            .get(token_series_id)
+            .expect("Token series not exist");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_series.creator_id,
+            "Skins: Creator only"
+        );
+    }
+
+    /// Checks the sale phase and per wallet cap of buyer_id, returns the sale config when its allowlist prices apply
+    pub(crate) fn internal_check_purchase<'a>(
+        &self,
+        token_series_id: &TokenSeriesId,
+        token_series: &'a TokenSeries,
+        buyer_id: &AccountId,
+        quantity: u32,
+    ) -> Result<Option<&'a SaleConfig>, String> {
+        let key = (token_series_id.clone(), buyer_id.clone());
+
+        let sale_config = match &token_series.sale_config {
+            Some(sale_config) => sale_config,
+            None => return Ok(None),
+        };
+
+        let mut allowlist_sale_config = None;
+        if sale_config.phase == SalePhase::Allowlist {
+            if !self.sale_allowlist.contains(&key) {
+                return Err(format!("Skins: {} is not allowlisted", buyer_id));
+            }
+            allowlist_sale_config = Some(sale_config);
+        }
+        if let Some(per_wallet_cap) = sale_config.per_wallet_cap {
+            let purchases = self.purchases_by_account.get(&key).unwrap_or(0);
+            if purchases + quantity > per_wallet_cap {
+                return Err(format!("Skins: per wallet cap of {} reached", per_wallet_cap));
+            }
+        }
+        Ok(allowlist_sale_config)
+    }
+
+    pub(crate) fn internal_record_purchase(
+        &mut self,
+        token_series_id: &TokenSeriesId,
+        buyer_id: &AccountId,
+        quantity: u32,
+    ) {
+        let key = (token_series_id.clone(), buyer_id.clone());
+        let purchases = self.purchases_by_account.get(&key).unwrap_or(0);
+        self.purchases_by_account.insert(&key, &(purchases + quantity));
+    }
+
+    pub(crate) fn internal_add_sale_proceeds(
+        &mut self,
+        ft_contract_id: &AccountId,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        if amount == 0 {
+            return;
+        }
+        let key = (ft_contract_id.clone(), account_id.clone());
+        let balance = self.sale_proceeds.get(&key).unwrap_or(0);
+        self.sale_proceeds.insert(&key, &(balance + amount));
+    }
+
+    /// ft_transfer from the contract, the amount goes to the sale proceeds of account_id when it fails
+    pub(crate) fn internal_send_sale_proceeds(
+        &self,
+        ft_contract_id: AccountId,
+        account_id: AccountId,
+        amount: Balance,
+        memo: &str,
+    ) -> Promise {
+        ft_transfer(&ft_contract_id, &account_id, amount, memo).then(
+            ext_sale_proceeds_resolver::resolve_withdraw_sale_proceeds(
+                ft_contract_id,
+                account_id,
+                U128(amount),
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_WITHDRAW_SALE_PROCEEDS,
+            ),
+        )
+    }
+}
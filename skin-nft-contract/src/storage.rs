@@ -0,0 +1,69 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Deposits NEAR for the storage of actions without an attached deposit, returns the balance
+    #[payable]
+    pub fn storage_deposit(&mut self, account_id: Option<ValidAccountId>) -> U128 {
+        let initial_storage_usage = env::storage_usage();
+        let account_id: AccountId = account_id
+            .map(|account_id| account_id.into())
+            .unwrap_or_else(env::predecessor_account_id);
+
+        let balance = self.storage_deposits.get(&account_id).unwrap_or(0) + env::attached_deposit();
+        self.storage_deposits.insert(&account_id, &balance);
+
+        // the entry itself is paid from the deposit
+        let required_cost = env::storage_byte_cost()
+            * Balance::from(env::storage_usage().saturating_sub(initial_storage_usage));
+        assert!(
+            required_cost <= balance,
+            "Must attach {} yoctoNEAR to cover storage",
+            required_cost,
+        );
+        self.storage_deposits.insert(&account_id, &(balance - required_cost));
+
+        U128(balance - required_cost)
+    }
+
+    /// Sends amount (the whole balance by default) of the storage deposit back to the caller
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> U128 {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+
+        let balance = self.storage_deposits.get(&account_id).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(balance);
+        assert!(amount <= balance, "Skins: storage deposit of {} is lower than {}", balance, amount);
+
+        self.storage_deposits.insert(&account_id, &(balance - amount));
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        U128(balance - amount)
+    }
+
+    pub fn storage_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        U128(self.storage_deposits.get(account_id.as_ref()).unwrap_or(0))
+    }
+}
+
+impl Contract {
+    /// Takes the cost of storage_used from the storage deposit of account_id
+    pub(crate) fn internal_charge_storage(&mut self, account_id: &AccountId, storage_used: u64) {
+        let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
+        if required_cost == 0 {
+            return;
+        }
+
+        let balance = self.storage_deposits.get(account_id).unwrap_or(0);
+        assert!(
+            required_cost <= balance,
+            "Skins: storage deposit of {} does not cover {} yoctoNEAR of storage",
+            balance,
+            required_cost,
+        );
+        self.storage_deposits.insert(account_id, &(balance - required_cost));
+    }
+}
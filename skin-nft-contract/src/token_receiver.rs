@@ -1,49 +1,376 @@
-use crate::*;
-use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-
-#[derive(Serialize, Deserialize)]
-#[serde(crate = "near_sdk::serde")]
-pub struct FuseArgs {
-    pub token_ids: Vec<TokenId>,
-    pub target_token_series_id: TokenSeriesId
-}
-
-#[near_bindgen]
-impl FungibleTokenReceiver for Contract {
-    fn ft_on_transfer(
-        &mut self,
-        sender_id: ValidAccountId,
-        amount: U128,
-        msg: String,
-    ) -> PromiseOrValue<U128> {
-        let ft_contract_id = env::predecessor_account_id();
-        let signer_id = env::signer_account_id();
-
-        assert_ne!(
-            ft_contract_id, signer_id,
-            "Skin: ft_on_approve should only be called via cross-contract call"
-        );
-
-        let FuseArgs {token_ids, target_token_series_id} = near_sdk::serde_json::from_str(&msg).expect("Not valid FuseArgs");
-
-        let fuse_cost= self.token_series_by_id.get(&target_token_series_id).unwrap().fuse_cost.unwrap();
-
-        assert_eq!(
-            fuse_cost.0,
-            ft_contract_id,
-            "Skin: Fungible token contract does not match"
-        );
-
-        assert!(
-            amount.0 >= fuse_cost.1.0,
-            "Skin: amount is lower than requirement"
-        );
-
-        let result = self.internal_fuse(token_ids, target_token_series_id, sender_id.to_string());
-        if result.is_some() {
-            PromiseOrValue::Value(U128(0))
-        } else {
-            panic!("Skin: not minted");
-        }
-    }
-}
\ No newline at end of file
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+
+/// latest version of the ft_on_transfer msg
+pub const FT_ON_TRANSFER_MSG_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FuseArgs {
+    pub token_ids: Vec<TokenId>,
+    pub target_token_series_id: TokenSeriesId
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BuyArgs {
+    pub token_series_id: TokenSeriesId,
+    /// sender_id when None
+    pub receiver_id: Option<ValidAccountId>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RandomLootArgs {
+    pub loot_box_id: LootBoxId,
+    /// sha256 of the salt given to nft_reveal_random_loot
+    pub salt_hash: Base64VecU8,
+    /// key token of sender_id when the loot box has a key
+    pub key_token_id: Option<TokenId>,
+    /// sender_id when None
+    pub receiver_id: Option<ValidAccountId>,
+}
+
+/// fuse of a single token into target_token_series_id
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UpgradeArgs {
+    pub token_id: TokenId,
+    pub target_token_series_id: TokenSeriesId,
+}
+
+/// commit of a chance fuse of the sender's workbench, revealed with nft_reveal_fuse
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommitFuseArgs {
+    pub target_token_series_id: TokenSeriesId,
+    /// sha256 of the salt given to nft_reveal_fuse
+    pub salt_hash: Base64VecU8,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FtOnTransferMsg {
+    Fuse(FuseArgs),
+    Buy(BuyArgs),
+    RandomLoot(RandomLootArgs),
+    Upgrade(UpgradeArgs),
+    CommitFuse(CommitFuseArgs),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VersionedFtOnTransferMsg {
+    #[serde(default = "default_msg_version")]
+    pub version: u32,
+    #[serde(flatten)]
+    pub msg: FtOnTransferMsg,
+}
+
+fn default_msg_version() -> u32 {
+    FT_ON_TRANSFER_MSG_VERSION
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Returns the unused amount, the whole amount when the action fails
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: ValidAccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let ft_contract_id = env::predecessor_account_id();
+        let signer_id = env::signer_account_id();
+
+        assert_ne!(
+            ft_contract_id, signer_id,
+            "Skins: ft_on_approve should only be called via cross-contract call"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        let sender_id: AccountId = sender_id.into();
+        let result = parse_ft_on_transfer_msg(&msg).and_then(|msg| {
+            self.internal_ft_on_transfer(&ft_contract_id, sender_id.clone(), amount.0, msg)
+        });
+
+        match result {
+            Ok(price) => {
+                // a panic reverts the action and ft_resolve_transfer refunds the whole amount
+                self.internal_charge_storage(
+                    &sender_id,
+                    env::storage_usage().saturating_sub(initial_storage_usage),
+                );
+                PromiseOrValue::Value(U128(amount.0 - price))
+            }
+            Err(err) => {
+                env::log(
+                    json!({
+                        "type": "ft_on_transfer_refund",
+                        "params": {
+                            "ft_contract_id": ft_contract_id,
+                            "amount": amount,
+                            "reason": err,
+                        }
+                    })
+                    .to_string()
+                    .as_bytes(),
+                );
+                PromiseOrValue::Value(amount)
+            }
+        }
+    }
+}
+
+impl Contract {
+    /// Returns the price taken from amount
+    fn internal_ft_on_transfer(
+        &mut self,
+        ft_contract_id: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+        msg: FtOnTransferMsg,
+    ) -> Result<Balance, String> {
+        match msg {
+            FtOnTransferMsg::Fuse(args) => {
+                self.internal_ft_fuse(ft_contract_id, sender_id, amount, args)
+            }
+            FtOnTransferMsg::Buy(args) => {
+                self.internal_ft_buy(ft_contract_id, sender_id, amount, args)
+            }
+            FtOnTransferMsg::RandomLoot(args) => {
+                self.internal_ft_random_loot(ft_contract_id, sender_id, amount, args)
+            }
+            FtOnTransferMsg::CommitFuse(args) => {
+                self.internal_ft_commit_fuse(ft_contract_id, sender_id, amount, args)
+            }
+            FtOnTransferMsg::Upgrade(UpgradeArgs {token_id, target_token_series_id}) => {
+                self.internal_ft_fuse(
+                    ft_contract_id,
+                    sender_id,
+                    amount,
+                    FuseArgs {
+                        token_ids: vec![token_id],
+                        target_token_series_id,
+                    },
+                )
+            }
+        }
+    }
+
+    /// Returns the fuse price taken from amount
+    fn internal_ft_fuse(
+        &mut self,
+        ft_contract_id: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+        args: FuseArgs,
+    ) -> Result<Balance, String> {
+        let FuseArgs {token_ids, target_token_series_id} = args;
+
+        if !self.approved_ft_token_ids.contains(ft_contract_id) {
+            return Err("Skins: Fungible token contract is not approved".to_string());
+        }
+
+        let token_series = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?;
+        let price = token_series
+            .fuse_cost
+            .and_then(|fuse_cost| fuse_cost.get(ft_contract_id).cloned())
+            .ok_or_else(|| "Skins: Fungible token contract does not match".to_string())?;
+
+        if amount < price.0 {
+            return Err("Skins: amount is lower than requirement".to_string());
+        }
+
+        self.internal_fuse(token_ids, target_token_series_id, &sender_id, sender_id.clone(), None)?;
+        self.internal_collect_fuse_fee(ft_contract_id, &token_series.creator_id, price.0);
+        Ok(price.0)
+    }
+
+    /// Commits a chance fuse of the sender's workbench for its FT fuse cost, returns the price taken from amount
+    fn internal_ft_commit_fuse(
+        &mut self,
+        ft_contract_id: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+        args: CommitFuseArgs,
+    ) -> Result<Balance, String> {
+        let CommitFuseArgs {target_token_series_id, salt_hash} = args;
+
+        if !self.approved_ft_token_ids.contains(ft_contract_id) {
+            return Err("Skins: Fungible token contract is not approved".to_string());
+        }
+
+        let price = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?
+            .fuse_cost
+            .and_then(|fuse_cost| fuse_cost.get(ft_contract_id).cloned())
+            .ok_or_else(|| "Skins: Fungible token contract does not match".to_string())?;
+        if amount < price.0 {
+            return Err("Skins: amount is lower than requirement".to_string());
+        }
+
+        self.internal_commit_fuse(
+            &sender_id,
+            target_token_series_id,
+            FuseRequest {
+                salt_hash,
+                ft_contract_id: Some(ft_contract_id.clone()),
+                price,
+                block_index: U64(env::block_index()),
+            },
+        )?;
+        Ok(price.0)
+    }
+
+    /// Mints a token of args.token_series_id for its ft_price, returns the price taken from amount
+    fn internal_ft_buy(
+        &mut self,
+        ft_contract_id: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+        args: BuyArgs,
+    ) -> Result<Balance, String> {
+        let BuyArgs {token_series_id, receiver_id} = args;
+        let receiver_id: AccountId = receiver_id
+            .map(|receiver_id| receiver_id.into())
+            .unwrap_or_else(|| sender_id.clone());
+
+        if !self.approved_ft_token_ids.contains(ft_contract_id) {
+            return Err("Skins: Fungible token contract is not approved".to_string());
+        }
+
+        let token_series = self
+            .token_series_by_id
+            .get(&token_series_id)
+            .ok_or_else(|| "Skins: Token series not exist".to_string())?;
+        let ft_price = token_series
+            .ft_price
+            .as_ref()
+            .filter(|ft_price| &ft_price.ft_contract_id == ft_contract_id)
+            .map(|ft_price| ft_price.price.0)
+            .ok_or_else(|| "Skins: Fungible token contract does not match".to_string())?;
+        // a flat ft_price can't follow the curve
+        if token_series.price_curve.is_some() {
+            return Err("Skins: series with a price_curve can't be bought with FT".to_string());
+        }
+
+        match token_series.sale_status() {
+            SaleStatus::Upcoming => return Err("Skins: sale not started".to_string()),
+            SaleStatus::Ended => return Err("Skins: sale ended".to_string()),
+            _ => {}
+        }
+        self.internal_check_supply(&[FuseOutput {
+            token_series_id: token_series_id.clone(),
+            quantity: 1,
+        }])?;
+        let price = self
+            .internal_check_purchase(&token_series_id, &token_series, &sender_id, 1)?
+            .and_then(|sale_config| sale_config.allowlist_ft_price)
+            .map_or(ft_price, |allowlist_ft_price| allowlist_ft_price.0);
+        if amount < price {
+            return Err("Skins: amount is lower than requirement".to_string());
+        }
+
+        self.internal_record_purchase(&token_series_id, &sender_id, 1);
+        let token_id = self._nft_mint_series(token_series_id.clone(), receiver_id.clone());
+
+        let payout = self.internal_primary_sale_payout(&token_series, price);
+        for (account_id, amount) in payout.payout.iter() {
+            self.internal_add_sale_proceeds(ft_contract_id, account_id, amount.0);
+        }
+
+        env::log(
+            json!({
+                "type": "nft_buy_payout",
+                "params": {
+                    "token_series_id": token_series_id,
+                    "token_ids": [token_id],
+                    "ft_contract_id": ft_contract_id,
+                    "price": U128(price),
+                    "payout": payout.payout,
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        );
+
+        NearEvent::log_nft_mint(
+            receiver_id,
+            vec![token_id],
+            Some(json!({"price": price.to_string(), "ft_contract_id": ft_contract_id}).to_string()),
+        );
+
+        Ok(price)
+    }
+
+    /// Commits an opening of args.loot_box_id for its ft_price, the price goes to the treasury at reveal
+    fn internal_ft_random_loot(
+        &mut self,
+        ft_contract_id: &AccountId,
+        sender_id: AccountId,
+        amount: Balance,
+        args: RandomLootArgs,
+    ) -> Result<Balance, String> {
+        let RandomLootArgs {loot_box_id, salt_hash, key_token_id, receiver_id} = args;
+        let receiver_id: AccountId = receiver_id
+            .map(|receiver_id| receiver_id.into())
+            .unwrap_or_else(|| sender_id.clone());
+
+        if !self.approved_ft_token_ids.contains(ft_contract_id) {
+            return Err("Skins: Fungible token contract is not approved".to_string());
+        }
+
+        let price = self
+            .loot_boxes
+            .get(&loot_box_id)
+            .ok_or_else(|| format!("Skins: loot box {} not exist", loot_box_id))?
+            .ft_price
+            .filter(|ft_price| &ft_price.ft_contract_id == ft_contract_id)
+            .map(|ft_price| ft_price.price.0)
+            .ok_or_else(|| "Skins: Fungible token contract does not match".to_string())?;
+        if amount < price {
+            return Err("Skins: amount is lower than requirement".to_string());
+        }
+
+        self.internal_commit_random_loot(LootRequest {
+            loot_box_id,
+            account_id: sender_id,
+            receiver_id,
+            salt_hash,
+            key_token_id,
+            ft_contract_id: Some(ft_contract_id.clone()),
+            price: U128(price),
+            block_index: U64(env::block_index()),
+        })?;
+        Ok(price)
+    }
+}
+
+/// Tagged msg, or FuseArgs alone for senders from before the action tag
+fn parse_ft_on_transfer_msg(msg: &str) -> Result<FtOnTransferMsg, String> {
+    let msg: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(msg)
+        .map_err(|_| "Skins: Not valid ft_on_transfer msg".to_string())?;
+    // the version decides how the rest of the msg is read
+    if let Some(version) = msg.get("version") {
+        if version.as_u64() != Some(FT_ON_TRANSFER_MSG_VERSION as u64) {
+            return Err(format!("Skins: msg version {} not supported", version));
+        }
+    }
+    // only a msg without action is read as FuseArgs, a bad tagged msg must not fuse
+    if msg.get("action").is_none() {
+        return near_sdk::serde_json::from_value::<FuseArgs>(msg)
+            .map(FtOnTransferMsg::Fuse)
+            .map_err(|_| "Skins: Not valid ft_on_transfer msg".to_string());
+    }
+    near_sdk::serde_json::from_value::<VersionedFtOnTransferMsg>(msg)
+        .map(|versioned_msg| versioned_msg.msg)
+        .map_err(|err| format!("Skins: Not valid ft_on_transfer msg: {}", err))
+}
@@ -0,0 +1,225 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves token_ids of the caller into the contract, on the workbench for target_token_series_id
+    #[payable]
+    pub fn nft_fuse_workbench_deposit(
+        &mut self,
+        target_token_series_id: TokenSeriesId,
+        token_ids: Vec<TokenId>,
+    ) -> Vec<TokenId> {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        assert!(!token_ids.is_empty(), "Skins: token_ids is empty");
+        self.internal_check_no_fuse_request(&account_id, &target_token_series_id)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        let fuse_requirements = self
+            .token_series_by_id
+            .get(&target_token_series_id)
+            .and_then(|token_series| to_fuse_recipes(token_series.fuse_requirements))
+            .expect("Skins: Token series is not fusable");
+
+        let mut workbench = self.internal_workbench(&account_id, &target_token_series_id);
+        workbench.extend(token_ids.iter().cloned());
+        assert!(
+            self.internal_fits_fuse_requirement(&workbench, &fuse_requirements),
+            "Skins: token_ids do not fit fuse_requirements"
+        );
+
+        let contract_id = env::current_account_id();
+        for token_id in token_ids.iter() {
+            self.tokens
+                .internal_transfer(&account_id, &contract_id, token_id, None, None);
+        }
+        NearEvent::log_nft_transfer(account_id.clone(), contract_id, token_ids, None, None);
+
+        self.internal_set_workbench(&account_id, &target_token_series_id, workbench.clone());
+
+        // the last token leaving tokens_per_owner can free more storage than the workbench takes
+        refund_deposit(
+            env::storage_usage().saturating_sub(initial_storage_usage),
+            0,
+        );
+        workbench
+    }
+
+    /// Returns token_ids, or the whole workbench when None, to the caller
+    #[payable]
+    pub fn nft_fuse_workbench_withdraw(
+        &mut self,
+        target_token_series_id: TokenSeriesId,
+        token_ids: Option<Vec<TokenId>>,
+    ) -> Vec<TokenId> {
+        assert_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        self.internal_check_no_fuse_request(&account_id, &target_token_series_id)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+
+        let mut workbench = self.internal_workbench(&account_id, &target_token_series_id);
+        let token_ids = match token_ids {
+            Some(token_ids) => {
+                for token_id in token_ids.iter() {
+                    let pos = workbench
+                        .iter()
+                        .position(|workbench_token_id| workbench_token_id == token_id)
+                        .unwrap_or_else(|| {
+                            env::panic(format!("Skins: token_id {} not in workbench", token_id).as_bytes())
+                        });
+                    workbench.remove(pos);
+                }
+                token_ids
+            }
+            None => std::mem::take(&mut workbench),
+        };
+
+        self.internal_set_workbench(&account_id, &target_token_series_id, workbench);
+        self.internal_return_tokens(&account_id, &token_ids);
+        refund_freed_storage(&account_id, initial_storage_usage);
+        token_ids
+    }
+
+    /// Fuses the whole workbench, attach the NEAR fuse cost and storage like nft_fuse
+    #[payable]
+    pub fn nft_fuse_workbench_finalize(&mut self, target_token_series_id: TokenSeriesId) -> FuseOutcome {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let (creator_id, fuse_price) = self.internal_assert_near_fuse_payment(&target_token_series_id);
+
+        let workbench = self.internal_workbench(&account_id, &target_token_series_id);
+        let fuse_outcome = self
+            .internal_fuse_workbench(&account_id, target_token_series_id, workbench, None)
+            .unwrap_or_else(|err| env::panic(err.as_bytes()));
+        self.internal_pay_near_fuse_fee(&creator_id, fuse_price);
+
+        // the emptied workbench can free more storage than the minted tokens take
+        refund_deposit(
+            env::storage_usage().saturating_sub(initial_storage_usage),
+            fuse_price,
+        );
+
+        fuse_outcome
+    }
+
+    pub fn nft_get_fuse_workbench(
+        &self,
+        account_id: ValidAccountId,
+        target_token_series_id: TokenSeriesId,
+    ) -> Vec<TokenId> {
+        self.internal_workbench(account_id.as_ref(), &target_token_series_id)
+    }
+
+    /// workbenches of account_id by target_token_series_id
+    pub fn nft_get_fuse_workbenches(&self, account_id: ValidAccountId) -> HashMap<TokenSeriesId, Vec<TokenId>> {
+        self.fuse_workbenches
+            .get(account_id.as_ref())
+            .unwrap_or_default()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_workbench(
+        &self,
+        account_id: &AccountId,
+        target_token_series_id: &TokenSeriesId,
+    ) -> Vec<TokenId> {
+        self.fuse_workbenches
+            .get(account_id)
+            .and_then(|mut workbenches| workbenches.remove(target_token_series_id))
+            .unwrap_or_default()
+    }
+
+    /// Removes the workbench when token_ids is empty
+    pub(crate) fn internal_set_workbench(
+        &mut self,
+        account_id: &AccountId,
+        target_token_series_id: &TokenSeriesId,
+        token_ids: Vec<TokenId>,
+    ) {
+        let mut workbenches = self.fuse_workbenches.get(account_id).unwrap_or_default();
+        if token_ids.is_empty() {
+            workbenches.remove(target_token_series_id);
+        } else {
+            workbenches.insert(target_token_series_id.clone(), token_ids);
+        }
+
+        if workbenches.is_empty() {
+            self.fuse_workbenches.remove(account_id);
+        } else {
+            self.fuse_workbenches.insert(account_id, &workbenches);
+        }
+    }
+
+    /// Fuses token_ids held by the contract for account_id and returns the unburned ones
+    pub(crate) fn internal_fuse_workbench(
+        &mut self,
+        account_id: &AccountId,
+        target_token_series_id: TokenSeriesId,
+        token_ids: Vec<TokenId>,
+        random_seed: Option<&[u8]>,
+    ) -> Result<FuseOutcome, String> {
+        let contract_id = env::current_account_id();
+        let fuse_outcome = self.internal_fuse(
+            token_ids.clone(),
+            target_token_series_id.clone(),
+            &contract_id,
+            account_id.clone(),
+            random_seed,
+        )?;
+        self.internal_set_workbench(account_id, &target_token_series_id, vec![]);
+
+        let unburned_token_ids: Vec<TokenId> = token_ids
+            .into_iter()
+            .filter(|token_id| !fuse_outcome.burned_token_ids.contains(token_id))
+            .collect();
+        self.internal_return_tokens(account_id, &unburned_token_ids);
+        Ok(fuse_outcome)
+    }
+
+    /// every token can take a slot of the same fuse_requirement
+    pub(crate) fn internal_fits_fuse_requirement(
+        &self,
+        token_ids: &[TokenId],
+        fuse_requirements: &[FuseRecipe],
+    ) -> bool {
+        let token_series_ids: Vec<TokenSeriesId> = token_ids
+            .iter()
+            .map(|token_id| token_id.split(TOKEN_DELIMETER).next().unwrap().to_string())
+            .collect();
+        let series_metadata = self.internal_series_metadata(&token_series_ids);
+        let tokens: Vec<(&str, &TokenMetadata)> = token_series_ids
+            .iter()
+            .map(|token_series_id| (token_series_id.as_str(), &series_metadata[token_series_id]))
+            .collect();
+
+        fuse_requirements.iter().any(|fuse_requirement| {
+            fuse_requirement
+                .assign(&tokens)
+                .iter()
+                .filter(|token_idx| token_idx.is_some())
+                .count()
+                == tokens.len()
+        })
+    }
+
+    /// Transfers token_ids held by the contract to account_id
+    pub(crate) fn internal_return_tokens(&mut self, account_id: &AccountId, token_ids: &[TokenId]) {
+        if token_ids.is_empty() {
+            return;
+        }
+        let contract_id = env::current_account_id();
+        for token_id in token_ids {
+            self.tokens
+                .internal_transfer(&contract_id, account_id, token_id, None, None);
+        }
+        NearEvent::log_nft_transfer(
+            contract_id,
+            account_id.clone(),
+            token_ids.to_vec(),
+            None,
+            None,
+        );
+    }
+}
@@ -246,4 +246,94 @@ fn simulate_buy() {
     let diff_after_sell_root = root.account().unwrap().amount - root_balance;
 
     assert_eq!(for_seller, diff_after_sell_root);
-}
\ No newline at end of file
+}
+
+#[test]
+fn simulate_buy_refund_excess_deposit() {
+    let (root, nft) = init();
+
+    let alice = root.create_user("alice".to_string(), to_yocto("100"));
+
+    root.call(
+        nft.account_id(),
+        "nft_create_series",
+        &json!({
+            "token_metadata": {
+                "title": "A".repeat(200),
+                "reference": "A".repeat(59),
+                "media": "A".repeat(59),
+                "copies": 100u64,
+            },
+            "price": to_yocto("1").to_string(),
+            "royalty": {
+                "0".repeat(64): 1000u32
+            },
+        }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("1")
+    );
+
+    let alice_balance = alice.account().unwrap().amount;
+
+    alice.call(
+        nft.account_id(),
+        "nft_buy",
+        &json!({
+            "token_series_id": "1",
+            "receiver_id": alice.account_id(),
+        }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("5")
+    ).assert_success();
+
+    let spent = alice_balance - alice.account().unwrap().amount;
+
+    // price + storage + gas, the rest of the 5 NEAR is refunded
+    assert!(spent > to_yocto("1"));
+    assert!(spent < to_yocto("1") + STORAGE_MINT_ESTIMATE + to_yocto("0.05"));
+}
+
+#[test]
+fn simulate_buy_without_storage_deposit() {
+    let (root, nft) = init();
+
+    let alice = root.create_user("alice".to_string(), to_yocto("100"));
+
+    root.call(
+        nft.account_id(),
+        "nft_create_series",
+        &json!({
+            "token_metadata": {
+                "title": "A".repeat(200),
+                "reference": "A".repeat(59),
+                "media": "A".repeat(59),
+                "copies": 100u64,
+            },
+            "price": to_yocto("1").to_string(),
+            "royalty": {
+                "0".repeat(64): 1000u32
+            },
+        }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("1")
+    );
+
+    let alice_balance = alice.account().unwrap().amount;
+
+    let outcome = alice.call(
+        nft.account_id(),
+        "nft_buy",
+        &json!({
+            "token_series_id": "1",
+            "receiver_id": alice.account_id(),
+        }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("1")
+    );
+
+    assert!(!outcome.is_ok());
+
+    // only gas is spent, the deposit is returned
+    let spent = alice_balance - alice.account().unwrap().amount;
+    assert!(spent < to_yocto("0.05"));
+}